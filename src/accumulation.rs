@@ -0,0 +1,124 @@
+use crate::color::Radiance;
+
+/// Running sums of all the samples traced for a pixel.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelEstimate {
+    /// Sum of every sample multiplied by its filter weight.
+    pub weighted_sum: Radiance,
+    pub total_weight: f32,
+    /// Sum of every sample, used when the filter weights add up to almost nothing.
+    pub unweighted_sum: Radiance,
+    pub sample_count: u32,
+}
+
+impl PixelEstimate {
+    pub fn add_sample(&mut self, sample: Radiance, weight: f32) {
+        self.weighted_sum += sample * weight;
+        self.total_weight += weight;
+        self.unweighted_sum += sample;
+        self.sample_count += 1;
+    }
+
+    pub fn merge(&mut self, other: &PixelEstimate) {
+        self.weighted_sum += other.weighted_sum;
+        self.total_weight += other.total_weight;
+        self.unweighted_sum += other.unweighted_sum;
+        self.sample_count += other.sample_count;
+    }
+
+    /// The filtered radiance of the pixel.
+    pub fn resolve(&self) -> Radiance {
+        if self.sample_count == 0 {
+            return Radiance::black();
+        }
+
+        // Filters with negative lobes can end up with almost no weight when there are
+        // few samples, the plain average is a better estimate in that case.
+        if self.total_weight > 1e-3 {
+            let filtered = self.weighted_sum / self.total_weight;
+            Radiance::new(
+                filtered.r.max(0.0),
+                filtered.g.max(0.0),
+                filtered.b.max(0.0),
+            )
+        } else {
+            self.unweighted_sum / self.sample_count as f32
+        }
+    }
+}
+
+/// Keeps adding up the samples of consecutive frames of the same view,
+/// so an image that doesn't change converges to a clean result over time.
+#[derive(Debug)]
+pub struct AccumulationBuffer {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<PixelEstimate>,
+    passes: u32,
+}
+
+impl AccumulationBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        AccumulationBuffer {
+            width,
+            height,
+            pixels: vec![PixelEstimate::default(); width * height],
+            passes: 0,
+        }
+    }
+
+    /// Throws away everything accumulated, must be called whenever the view changes.
+    pub fn reset(&mut self) {
+        self.pixels.fill(PixelEstimate::default());
+        self.passes = 0;
+    }
+
+    /// How many frames have been accumulated since the last reset.
+    pub fn passes(&self) -> u32 {
+        self.passes
+    }
+
+    /// How many samples every pixel has received since the last reset.
+    pub fn samples_per_pixel(&self) -> u32 {
+        self.pixels.first().map_or(0, |p| p.sample_count)
+    }
+
+    /// Adds a new frame, `frame` must have one estimate for every pixel in row order.
+    pub fn add_pass(&mut self, frame: &[PixelEstimate]) {
+        for (pixel, estimate) in self.pixels.iter_mut().zip(frame) {
+            pixel.merge(estimate);
+        }
+        self.passes += 1;
+    }
+
+    /// The radiance of every pixel in row order.
+    pub fn resolve(&self) -> Vec<Radiance> {
+        self.pixels.iter().map(PixelEstimate::resolve).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accumulated_passes_average_and_reset() {
+        let mut buffer = AccumulationBuffer::new(2, 1);
+        let pass = |value: f32| {
+            let mut estimate = PixelEstimate::default();
+            estimate.add_sample(Radiance::new(value, value, value), 1.0);
+            vec![estimate; 2]
+        };
+
+        buffer.add_pass(&pass(1.0));
+        buffer.add_pass(&pass(3.0));
+
+        assert_eq!(buffer.passes(), 2);
+        assert_eq!(buffer.samples_per_pixel(), 2);
+        assert_eq!(buffer.resolve()[1], Radiance::new(2.0, 2.0, 2.0));
+
+        buffer.reset();
+        assert_eq!(buffer.passes(), 0);
+        assert_eq!(buffer.resolve()[0], Radiance::black());
+    }
+}
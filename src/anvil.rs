@@ -0,0 +1,469 @@
+//! Reads blocks out of the Anvil region files (`r.<x>.<z>.mca`) of a saved Minecraft world.
+//!
+//! A region file holds 32x32 chunks, each of them zlib (or gzip) compressed NBT found through
+//! the location table at the start of the file. Chunks are split in sections of 16x16x16 blocks
+//! whose block states are indices into a palette packed in an array of longs.
+//!
+//! Worlds saved since Minecraft 1.13 are supported, older worlds used numeric block ids.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use glm::Vec3;
+
+use crate::{
+    minecraft::{block_name, is_air, BlockMaterials},
+    nbt::{read_gzip_nbt, read_nbt, read_zlib_nbt, NbtError, NbtTag},
+    schematic::Schematic,
+    voxel::VoxelGrid,
+};
+
+const SECTOR_SIZE: usize = 4096;
+const REGION_CHUNKS: i32 = 32;
+const SECTION_SIZE: usize = 16;
+/// The first data version whose block states don't span two longs (20w17a, before 1.16).
+const PADDED_BLOCK_STATES_VERSION: i64 = 2529;
+
+#[derive(Debug)]
+pub enum AnvilError {
+    Io(PathBuf, std::io::Error),
+    Nbt(PathBuf, NbtError),
+    Invalid(PathBuf, String),
+}
+
+impl Display for AnvilError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnvilError::Io(path, error) => {
+                write!(f, "Couldn't read region file {}: {error}", path.display())
+            }
+            AnvilError::Nbt(path, error) => {
+                write!(
+                    f,
+                    "Invalid chunk in region file {}: {error}",
+                    path.display()
+                )
+            }
+            AnvilError::Invalid(path, reason) => {
+                write!(f, "Invalid region file {}: {reason}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for AnvilError {}
+
+/// A box of blocks in world coordinates, both corners included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockBox {
+    pub min: [i32; 3],
+    pub max: [i32; 3],
+}
+
+impl BlockBox {
+    /// The box between two opposite corners, in any order.
+    pub fn new(corner: [i32; 3], opposite: [i32; 3]) -> Self {
+        BlockBox {
+            min: [0, 1, 2].map(|i| corner[i].min(opposite[i])),
+            max: [0, 1, 2].map(|i| corner[i].max(opposite[i])),
+        }
+    }
+
+    pub fn size(&self) -> [usize; 3] {
+        [0, 1, 2].map(|i| (self.max[i] - self.min[i] + 1) as usize)
+    }
+}
+
+/// A region file loaded in memory.
+pub struct RegionFile {
+    path: PathBuf,
+    data: Vec<u8>,
+}
+
+impl RegionFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AnvilError> {
+        let path = path.as_ref().to_path_buf();
+        let data = fs::read(&path).map_err(|e| AnvilError::Io(path.clone(), e))?;
+        Ok(RegionFile::from_bytes(path, data))
+    }
+
+    /// A region file whose contents are already in memory,
+    /// `path` is used to find chunks stored in their own file and in errors.
+    pub fn from_bytes(path: PathBuf, data: Vec<u8>) -> Self {
+        RegionFile { path, data }
+    }
+
+    fn invalid(&self, reason: String) -> AnvilError {
+        AnvilError::Invalid(self.path.clone(), reason)
+    }
+
+    /// Reads the chunk at `x`, `z` (0 to 31) inside the region, `None` if it was never generated.
+    pub fn read_chunk(&self, x: usize, z: usize) -> Result<Option<NbtTag>, AnvilError> {
+        let location = 4 * (x % 32 + (z % 32) * 32);
+        let Some(entry) = self.data.get(location..location + 4) else {
+            // Empty files are left by worlds that never saved a chunk in the region.
+            return Ok(None);
+        };
+        let sector = u32::from_be_bytes([0, entry[0], entry[1], entry[2]]) as usize;
+        if sector == 0 || entry[3] == 0 {
+            return Ok(None);
+        }
+
+        let start = sector * SECTOR_SIZE;
+        let header = self
+            .data
+            .get(start..start + 5)
+            .ok_or_else(|| self.invalid(format!("chunk {x}, {z} is outside of the file")))?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let compression = header[4];
+
+        // Chunks too big for the region file are saved next to it as `c.<x>.<z>.mcc`.
+        let external;
+        let compressed = if compression & 0x80 != 0 {
+            let (region_x, region_z) = self.region_coordinates()?;
+            let chunk_x = region_x * REGION_CHUNKS + x as i32;
+            let chunk_z = region_z * REGION_CHUNKS + z as i32;
+            let path = self
+                .path
+                .with_file_name(format!("c.{chunk_x}.{chunk_z}.mcc"));
+            external = fs::read(&path).map_err(|e| AnvilError::Io(path, e))?;
+            &external[..]
+        } else {
+            self.data
+                .get(start + 5..start + 4 + length)
+                .ok_or_else(|| self.invalid(format!("chunk {x}, {z} is cut short")))?
+        };
+
+        let nbt = match compression & 0x7F {
+            1 => read_gzip_nbt(compressed),
+            2 => read_zlib_nbt(compressed),
+            3 => read_nbt(compressed),
+            other => {
+                return Err(self.invalid(format!(
+                    "chunk {x}, {z} uses the unsupported compression {other}"
+                )))
+            }
+        };
+
+        let (_, chunk) = nbt.map_err(|e| AnvilError::Nbt(self.path.clone(), e))?;
+        Ok(Some(chunk))
+    }
+
+    /// The coordinates of the region, taken from the name of the file.
+    fn region_coordinates(&self) -> Result<(i32, i32), AnvilError> {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let coordinates: Vec<i32> = name
+            .split('.')
+            .skip(1)
+            .take(2)
+            .filter_map(|value| value.parse().ok())
+            .collect();
+
+        match coordinates[..] {
+            [x, z] => Ok((x, z)),
+            _ => Err(self.invalid("the name isn't `r.<x>.<z>.mca`".to_string())),
+        }
+    }
+}
+
+/// A 16x16x16 part of a chunk.
+struct Section {
+    /// Height of the section in sections, not blocks.
+    y: i32,
+    palette: Vec<String>,
+    /// Palette index of every block in YZX order, empty when the palette only has one entry.
+    states: Vec<usize>,
+}
+
+impl Section {
+    fn block(&self, x: usize, y: usize, z: usize) -> &str {
+        let index = (y * SECTION_SIZE + z) * SECTION_SIZE + x;
+        let state = self.states.get(index).copied().unwrap_or(0);
+        self.palette
+            .get(state)
+            .map_or("minecraft:air", String::as_str)
+    }
+}
+
+/// Unpacks the palette indices of the 4096 blocks of a section.
+fn unpack_states(data: &[i64], palette_len: usize, padded: bool) -> Vec<usize> {
+    let block_count = SECTION_SIZE * SECTION_SIZE * SECTION_SIZE;
+    let bits = (usize::BITS - (palette_len.max(1) - 1).leading_zeros()).max(4) as usize;
+    let mask = (1u64 << bits) - 1;
+
+    (0..block_count)
+        .map(|i| {
+            let value = if padded {
+                // Entries never span two longs, the leftover bits of each long are unused.
+                let per_long = 64 / bits;
+                let long = data.get(i / per_long).copied().unwrap_or(0) as u64;
+                long >> ((i % per_long) * bits)
+            } else {
+                let bit = i * bits;
+                let low = data.get(bit / 64).copied().unwrap_or(0) as u64 >> (bit % 64);
+                let high = if bit % 64 + bits > 64 {
+                    (data.get(bit / 64 + 1).copied().unwrap_or(0) as u64) << (64 - bit % 64)
+                } else {
+                    0
+                };
+                low | high
+            };
+            (value & mask) as usize
+        })
+        .collect()
+}
+
+/// Reads the sections of a chunk, both from the current format and the one of 1.13 to 1.17.
+fn chunk_sections(chunk: &NbtTag) -> Vec<Section> {
+    let padded = chunk
+        .get("DataVersion")
+        .and_then(NbtTag::as_i64)
+        .is_none_or(|version| version >= PADDED_BLOCK_STATES_VERSION);
+    let (sections, old_format) = match chunk.get("sections") {
+        Some(sections) => (sections, false),
+        None => match chunk.get("Level").and_then(|level| level.get("Sections")) {
+            Some(sections) => (sections, true),
+            None => return vec![],
+        },
+    };
+
+    sections
+        .as_list()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|section| {
+            let y = section.get("Y")?.as_i64()? as i32;
+            let (palette, data) = if old_format {
+                (section.get("Palette")?, section.get("BlockStates"))
+            } else {
+                let block_states = section.get("block_states")?;
+                (block_states.get("palette")?, block_states.get("data"))
+            };
+
+            let palette: Vec<String> = palette
+                .as_list()?
+                .iter()
+                .map(|state| {
+                    let name = state.get("Name").and_then(NbtTag::as_str);
+                    name.unwrap_or("minecraft:air").to_string()
+                })
+                .collect();
+            let states = data
+                .and_then(NbtTag::as_long_array)
+                .map(|data| unpack_states(data, palette.len(), padded))
+                .unwrap_or_default();
+
+            Some(Section { y, palette, states })
+        })
+        .collect()
+}
+
+/// Reads every block inside `bounds` from the region files in `region_dir`.
+///
+/// Regions and chunks that were never generated are left empty.
+pub fn read_region_blocks(
+    region_dir: impl AsRef<Path>,
+    bounds: &BlockBox,
+) -> Result<Schematic, AnvilError> {
+    let region_dir = region_dir.as_ref();
+    let mut regions: HashMap<(i32, i32), Option<RegionFile>> = HashMap::new();
+    let mut palette_indices: HashMap<String, usize> = HashMap::new();
+    let mut schematic = Schematic {
+        size: bounds.size(),
+        palette: vec![],
+        blocks: vec![],
+    };
+
+    // Chunks are as wide as their sections
+    let chunk_range = |axis: usize| {
+        bounds.min[axis].div_euclid(SECTION_SIZE as i32)
+            ..=bounds.max[axis].div_euclid(SECTION_SIZE as i32)
+    };
+    for chunk_z in chunk_range(2) {
+        for chunk_x in chunk_range(0) {
+            let region_key = (
+                chunk_x.div_euclid(REGION_CHUNKS),
+                chunk_z.div_euclid(REGION_CHUNKS),
+            );
+            let region = match regions.get(&region_key) {
+                Some(region) => region,
+                None => {
+                    let path = region_dir.join(format!("r.{}.{}.mca", region_key.0, region_key.1));
+                    let region = match RegionFile::open(&path) {
+                        Ok(region) => Some(region),
+                        Err(AnvilError::Io(_, error)) if error.kind() == ErrorKind::NotFound => {
+                            eprintln!(
+                                "Warning: {} doesn't exist, leaving it empty",
+                                path.display()
+                            );
+                            None
+                        }
+                        Err(error) => return Err(error),
+                    };
+                    regions.entry(region_key).or_insert(region)
+                }
+            };
+            let Some(region) = region else {
+                continue;
+            };
+            let Some(chunk) = region.read_chunk(
+                chunk_x.rem_euclid(REGION_CHUNKS) as usize,
+                chunk_z.rem_euclid(REGION_CHUNKS) as usize,
+            )?
+            else {
+                continue;
+            };
+
+            for section in chunk_sections(&chunk) {
+                // The part of the bounds inside the section, in world coordinates
+                let section_origin = [
+                    chunk_x * SECTION_SIZE as i32,
+                    section.y * SECTION_SIZE as i32,
+                    chunk_z * SECTION_SIZE as i32,
+                ];
+                let from = [0, 1, 2].map(|i| bounds.min[i].max(section_origin[i]));
+                let to = [0, 1, 2]
+                    .map(|i| bounds.max[i].min(section_origin[i] + SECTION_SIZE as i32 - 1));
+                if (0..3).any(|i| from[i] > to[i]) {
+                    continue;
+                }
+
+                for y in from[1]..=to[1] {
+                    for z in from[2]..=to[2] {
+                        for x in from[0]..=to[0] {
+                            let local =
+                                [x, y, z].map(|c| c.rem_euclid(SECTION_SIZE as i32) as usize);
+                            let state = section.block(local[0], local[1], local[2]);
+                            if is_air(block_name(state)) {
+                                continue;
+                            }
+
+                            let index =
+                                *palette_indices.entry(state.to_string()).or_insert_with(|| {
+                                    schematic.palette.push(state.to_string());
+                                    schematic.palette.len() - 1
+                                });
+                            let position =
+                                [0, 1, 2].map(|i| ([x, y, z][i] - bounds.min[i]) as usize);
+                            schematic.blocks.push((position, index));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(schematic)
+}
+
+/// Reads the blocks inside `bounds` from the region files in `region_dir` as a grid of blocks
+/// of `block_size` whose corner with the smallest coordinates is `origin`.
+pub fn load_region(
+    region_dir: impl AsRef<Path>,
+    bounds: &BlockBox,
+    origin: Vec3,
+    block_size: f32,
+    materials: &BlockMaterials,
+) -> Result<VoxelGrid, AnvilError> {
+    Ok(read_region_blocks(region_dir, bounds)?.to_voxel_grid(origin, block_size, materials))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::ZlibEncoder, Compression};
+
+    use super::*;
+
+    fn write_string(bytes: &mut Vec<u8>, value: &str) {
+        bytes.extend((value.len() as u16).to_be_bytes());
+        bytes.extend(value.as_bytes());
+    }
+
+    /// Encodes a section of a 1.18+ chunk with the given palette and the first block set to 1.
+    fn chunk_nbt(palette: &[&str]) -> Vec<u8> {
+        let mut bytes = vec![10];
+        write_string(&mut bytes, "");
+        bytes.push(3);
+        write_string(&mut bytes, "DataVersion");
+        bytes.extend(3465i32.to_be_bytes());
+
+        // sections: [{ Y: 4, block_states: { palette: [...], data: [...] } }]
+        bytes.push(9);
+        write_string(&mut bytes, "sections");
+        bytes.push(10);
+        bytes.extend(1i32.to_be_bytes());
+        bytes.push(1);
+        write_string(&mut bytes, "Y");
+        bytes.push(4);
+        bytes.push(10);
+        write_string(&mut bytes, "block_states");
+        bytes.push(9);
+        write_string(&mut bytes, "palette");
+        bytes.push(10);
+        bytes.extend((palette.len() as i32).to_be_bytes());
+        for name in palette {
+            bytes.push(8);
+            write_string(&mut bytes, "Name");
+            write_string(&mut bytes, name);
+            bytes.push(0);
+        }
+        // 4 bits per block, 16 blocks per long
+        bytes.push(12);
+        write_string(&mut bytes, "data");
+        bytes.extend(256i32.to_be_bytes());
+        bytes.extend(1i64.to_be_bytes());
+        bytes.extend([0; 255 * 8]);
+        bytes.extend([0, 0, 0]);
+
+        bytes
+    }
+
+    #[test]
+    fn test_reads_chunks_of_a_region() {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder
+            .write_all(&chunk_nbt(&["minecraft:air", "minecraft:obsidian"]))
+            .unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        // The chunk at 1, 2 inside the region starts at the third sector.
+        let mut data = vec![0; 2 * SECTOR_SIZE];
+        data[4 * (1 + 2 * 32)..][..4].copy_from_slice(&[0, 0, 2, 1]);
+        data.extend(((compressed.len() + 1) as u32).to_be_bytes());
+        data.push(2);
+        data.extend(compressed);
+        let region = RegionFile::from_bytes(PathBuf::from("r.0.0.mca"), data);
+
+        assert!(region.read_chunk(0, 0).unwrap().is_none());
+        let chunk = region.read_chunk(1, 2).unwrap().unwrap();
+        let sections = chunk_sections(&chunk);
+
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].y, 4);
+        assert_eq!(sections[0].block(0, 0, 0), "minecraft:obsidian");
+        assert_eq!(sections[0].block(1, 0, 0), "minecraft:air");
+    }
+
+    #[test]
+    fn test_unpacks_block_states_spanning_longs() {
+        // 5 bits per block, the 13th block starts at bit 60 and ends in the second long.
+        let mut data = vec![0i64; 320];
+        data[0] = 0b1011 << 60;
+        data[1] = 0b1;
+        let states = unpack_states(&data, 17, false);
+
+        assert_eq!(states[12], 0b11011);
+        assert_eq!(states[0], 0);
+    }
+}
@@ -0,0 +1,265 @@
+use nalgebra_glm::Vec3;
+use raytracer::accumulation::AccumulationBuffer;
+use raytracer::camera::Camera;
+use raytracer::framebuffer::Framebuffer;
+use raytracer::integrator::integrator_from_name;
+use raytracer::path_tracer::{PathTracer, PathTracerSettings};
+use raytracer::render::{render, render_progressive, RenderSettings};
+use raytracer::sampling::{PixelFilter, SamplePattern};
+use raytracer::scene_file::load_model;
+use raytracer::texture::TextureRegistry;
+use raytracer::tonemap::ToneMapOperator;
+use raytracer::world::nether_portal;
+use raytracer::TimeOfDay;
+use std::env;
+use std::process::exit;
+use std::sync::Arc;
+use std::time::Instant;
+
+const USAGE: &str = "Renders a single frame without opening a window.
+
+Usage: headless [asset_dir] [options]
+
+The asset directory has textures that replace the built-in ones, or is a Minecraft resource pack.
+
+Options:
+    --width <pixels>     Width of the image (default: 1080)
+    --height <pixels>    Height of the image (default: 720)
+    --output <path>      Where to save the image, .bmp or any format supported by `image` (default: render.png)
+    --scene <path>       Scene file (.json, .gltf or .glb) to render (default: the built-in nether portal)
+    --eye <x,y,z>        Position of the camera (default: the one of the scene)
+    --center <x,y,z>     Point the camera looks at (default: the one of the scene)
+    --time <day|night>   Time of day (default: the one of the scene)
+    --tonemap <name>     Tone mapping operator: clamp, reinhard, filmic or aces (default: clamp)
+    --exposure <stops>   Exposure compensation, every stop doubles the brightness (default: 0)
+    --auto-exposure      Adjust the exposure to the average luminance of the frame
+    --spp <count>        Samples traced for every pixel (default: 1)
+    --sampling <name>    Placement of the samples: stratified or jittered (default: stratified)
+    --filter <name>      Reconstruction filter: box, tent, gaussian or mitchell (default: box)
+    --passes <count>     Frames accumulated progressively, each one with --spp samples (default: 1)
+    --integrator <name>  How light is computed: whitted, path or normals (default: whitted)
+    --max-bounces <n>    Bounces after which paths are cut by the path tracer (default: 16)
+    --firefly-clamp <v>  Brightest an indirect bounce of the path tracer can be (default: 10)
+    --seed <n>           Seed of the built-in scene and of the sampling, the same seed renders the same image (default: 0)
+    --frame-time <secs>  Seconds into the animations of the animated textures (default: 0)";
+
+struct Options {
+    asset_dir: Option<String>,
+    width: usize,
+    height: usize,
+    output: String,
+    scene: Option<String>,
+    eye: Option<Vec3>,
+    center: Option<Vec3>,
+    daytime: Option<TimeOfDay>,
+    settings: RenderSettings,
+    passes: usize,
+}
+
+fn parse_vec3(value: &str) -> Result<Vec3, String> {
+    let components = value
+        .split(',')
+        .map(|c| c.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|e| format!("Invalid vector `{value}`: {e}"))?;
+
+    match components[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(format!("Invalid vector `{value}`: expected x,y,z")),
+    }
+}
+
+fn parse_size(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("Sizes must be greater than 0".to_string()),
+        Ok(size) => Ok(size),
+        Err(e) => Err(format!("Invalid size `{value}`: {e}")),
+    }
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut args = args.peekable();
+    let asset_dir = args.next_if(|arg| !arg.starts_with("--"));
+    let mut path_tracer = PathTracerSettings::default();
+    // The options of the path tracer that were given, they make no sense with other integrators
+    let mut path_tracer_flags = vec![];
+    let mut options = Options {
+        asset_dir,
+        width: 1080,
+        height: 720,
+        output: "render.png".to_string(),
+        scene: None,
+        eye: None,
+        center: None,
+        daytime: None,
+        settings: RenderSettings::default(),
+        passes: 1,
+    };
+
+    while let Some(flag) = args.next() {
+        if flag == "--auto-exposure" {
+            options.settings.display.auto_exposure = true;
+            continue;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for `{flag}`"))?;
+
+        match flag.as_str() {
+            "--width" => options.width = parse_size(&value)?,
+            "--height" => options.height = parse_size(&value)?,
+            "--output" => options.output = value,
+            "--scene" => options.scene = Some(value),
+            "--eye" => options.eye = Some(parse_vec3(&value)?),
+            "--center" => options.center = Some(parse_vec3(&value)?),
+            "--time" => {
+                options.daytime = match value.as_str() {
+                    "day" => Some(TimeOfDay::Day),
+                    "night" => Some(TimeOfDay::Night),
+                    _ => return Err(format!("Invalid time of day `{value}`")),
+                }
+            }
+            "--tonemap" => {
+                options.settings.display.operator = ToneMapOperator::from_name(&value)
+                    .ok_or_else(|| format!("Unknown tone mapping operator `{value}`"))?
+            }
+            "--exposure" => {
+                options.settings.display.exposure = value
+                    .parse()
+                    .map_err(|e| format!("Invalid exposure `{value}`: {e}"))?
+            }
+            "--spp" => {
+                options.settings.samples_per_pixel = parse_size(&value)?
+                    .try_into()
+                    .map_err(|_| format!("Too many samples per pixel `{value}`"))?
+            }
+            "--sampling" => {
+                options.settings.sample_pattern = SamplePattern::from_name(&value)
+                    .ok_or_else(|| format!("Unknown sample pattern `{value}`"))?
+            }
+            "--filter" => {
+                options.settings.filter = PixelFilter::from_name(&value)
+                    .ok_or_else(|| format!("Unknown filter `{value}`"))?
+            }
+            "--passes" => options.passes = parse_size(&value)?,
+            "--integrator" => {
+                options.settings.integrator = integrator_from_name(&value)
+                    .ok_or_else(|| format!("Unknown integrator `{value}`"))?
+            }
+            "--max-bounces" => {
+                path_tracer_flags.push(flag.clone());
+                path_tracer.max_bounces = value
+                    .parse()
+                    .map_err(|e| format!("Invalid amount of bounces `{value}`: {e}"))?
+            }
+            "--firefly-clamp" => {
+                path_tracer_flags.push(flag.clone());
+                path_tracer.firefly_clamp = value
+                    .parse()
+                    .map_err(|e| format!("Invalid firefly clamp `{value}`: {e}"))?
+            }
+            "--seed" => {
+                options.settings.seed = value
+                    .parse()
+                    .map_err(|e| format!("Invalid seed `{value}`: {e}"))?
+            }
+            "--frame-time" => {
+                options.settings.time = value
+                    .parse()
+                    .map_err(|e| format!("Invalid frame time `{value}`: {e}"))?
+            }
+            _ => return Err(format!("Unknown option `{flag}`")),
+        }
+    }
+
+    if options.settings.integrator.name() == "path" {
+        options.settings.integrator = Arc::new(PathTracer::new(path_tracer));
+    } else if let Some(flag) = path_tracer_flags.first() {
+        return Err(format!(
+            "`{flag}` can only be used with `--integrator path`"
+        ));
+    }
+
+    Ok(options)
+}
+
+fn main() {
+    let mut args = env::args();
+    args.next();
+
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}\n\n{USAGE}");
+            exit(1);
+        }
+    };
+
+    let textures = match &options.asset_dir {
+        Some(asset_dir) => {
+            println!("Reading assets from: {asset_dir}");
+            TextureRegistry::new(asset_dir)
+        }
+        None => TextureRegistry::builtin(),
+    };
+    let mut data = match &options.scene {
+        Some(scene_file) => match load_model(scene_file, textures) {
+            Ok(data) => data,
+            Err(error) => {
+                eprintln!("{error}");
+                exit(1);
+            }
+        },
+        None => nether_portal(textures, options.settings.seed),
+    };
+
+    if options.eye.is_some() || options.center.is_some() {
+        let eye = options.eye.unwrap_or(data.camera.eye);
+        let center = options.center.unwrap_or(data.camera.center);
+        data.camera = Camera::new(eye, center, data.camera.up);
+    }
+    if let Some(daytime) = options.daytime {
+        data.sky_color = daytime.sky_color();
+        data.top_light = daytime.top_light();
+        data.daytime = daytime;
+    }
+
+    let mut framebuffer = Framebuffer::new(options.width, options.height);
+
+    let start = Instant::now();
+    if options.passes > 1 {
+        let mut accumulation = AccumulationBuffer::new(options.width, options.height);
+        for _ in 0..options.passes {
+            render_progressive(
+                &mut framebuffer,
+                &mut accumulation,
+                &data,
+                &options.settings,
+            );
+        }
+    } else {
+        render(&mut framebuffer, &data, &options.settings);
+    }
+    println!(
+        "Rendered {}x{} at {} spp in {:.2?}",
+        options.width,
+        options.height,
+        options.settings.samples_per_pixel as usize * options.passes,
+        start.elapsed()
+    );
+
+    let result = if options.output.to_lowercase().ends_with(".bmp") {
+        framebuffer.save(&options.output).map_err(|e| e.to_string())
+    } else {
+        framebuffer
+            .save_image(&options.output)
+            .map_err(|e| e.to_string())
+    };
+
+    if let Err(error) = result {
+        eprintln!("Couldn't save the image to {}: {error}", options.output);
+        exit(1);
+    }
+    println!("Saved render to: {}", options.output);
+}
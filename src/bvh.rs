@@ -0,0 +1,325 @@
+use glm::Vec3;
+
+use crate::{
+    cube::BoxBounds,
+    raytracer::{Intersect, Traceable},
+};
+
+/// How many buckets are used to approximate the Surface Area Heuristic when splitting a node.
+const SAH_BUCKETS: usize = 12;
+/// Nodes with this amount of objects or less are never split.
+const MAX_LEAF_SIZE: usize = 2;
+/// Cost of visiting a node relative to the cost of intersecting an object.
+const TRAVERSAL_COST: f32 = 0.125;
+
+#[derive(Debug)]
+enum BvhNodeKind {
+    /// Contains the objects in `indices[first..first + count]`.
+    Leaf { first: usize, count: usize },
+    /// The left child is always the next node, so only the right one is stored.
+    Interior { right: usize },
+}
+
+#[derive(Debug)]
+struct BvhNode {
+    bounds: BoxBounds,
+    kind: BvhNodeKind,
+}
+
+/// Bounding Volume Hierarchy built with the Surface Area Heuristic.
+///
+/// It doesn't own the objects, it only stores indices to the slice it was built from,
+/// so it must always be queried with that same slice.
+#[derive(Debug, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+}
+
+struct BuildEntry {
+    index: usize,
+    bounds: BoxBounds,
+    centroid: Vec3,
+}
+
+impl Bvh {
+    /// Builds a new hierarchy over the given objects.
+    pub fn new(objects: &[Box<dyn Traceable>]) -> Self {
+        let mut entries: Vec<BuildEntry> = objects
+            .iter()
+            .enumerate()
+            .map(|(index, object)| {
+                let bounds = object.bounding_box();
+                BuildEntry {
+                    index,
+                    bounds,
+                    centroid: bounds.centroid(),
+                }
+            })
+            .collect();
+
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(2 * objects.len()),
+            indices: Vec::with_capacity(objects.len()),
+        };
+
+        if !entries.is_empty() {
+            bvh.build_node(&mut entries);
+        }
+
+        bvh
+    }
+
+    /// Recursively builds the node containing `entries` and returns its index.
+    fn build_node(&mut self, entries: &mut [BuildEntry]) -> usize {
+        let bounds = entries
+            .iter()
+            .fold(BoxBounds::empty(), |acc, e| acc.union(&e.bounds));
+
+        let node_index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounds,
+            kind: BvhNodeKind::Leaf { first: 0, count: 0 },
+        });
+
+        let split = if entries.len() > MAX_LEAF_SIZE {
+            find_sah_split(entries, &bounds)
+        } else {
+            None
+        };
+
+        match split {
+            Some(mid) => {
+                let (left, right) = entries.split_at_mut(mid);
+                self.build_node(left);
+                let right = self.build_node(right);
+                self.nodes[node_index].kind = BvhNodeKind::Interior { right };
+            }
+            None => {
+                let first = self.indices.len();
+                self.indices.extend(entries.iter().map(|e| e.index));
+                self.nodes[node_index].kind = BvhNodeKind::Leaf {
+                    first,
+                    count: entries.len(),
+                };
+            }
+        }
+
+        node_index
+    }
+
+    /// Bounds of the whole hierarchy.
+    pub fn bounds(&self) -> BoxBounds {
+        self.nodes
+            .first()
+            .map(|root| root.bounds)
+            .unwrap_or_else(BoxBounds::empty)
+    }
+
+    /// Finds the closest object the ray hits.
+    ///
+    /// * `objects`: The same objects the hierarchy was built from.
+    pub fn ray_intersect(
+        &self,
+        objects: &[Box<dyn Traceable>],
+        ray_origin: &Vec3,
+        ray_direction: &Vec3,
+    ) -> Option<Intersect> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inv_direction = Vec3::new(
+            1.0 / ray_direction.x,
+            1.0 / ray_direction.y,
+            1.0 / ray_direction.z,
+        );
+
+        let mut closest: Option<Intersect> = None;
+        let mut closest_distance = f32::INFINITY;
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            match node.bounds.ray_hit(ray_origin, &inv_direction) {
+                Some(distance) if distance <= closest_distance => {}
+                _ => continue,
+            }
+
+            match node.kind {
+                BvhNodeKind::Leaf { first, count } => {
+                    for &index in &self.indices[first..first + count] {
+                        if let Some(intersect) =
+                            objects[index].ray_intersect(ray_origin, ray_direction)
+                        {
+                            if intersect.distance < closest_distance {
+                                closest_distance = intersect.distance;
+                                closest = Some(intersect);
+                            }
+                        }
+                    }
+                }
+                BvhNodeKind::Interior { right } => {
+                    let left = node_index + 1;
+                    let left_hit = self.nodes[left].bounds.ray_hit(ray_origin, &inv_direction);
+                    let right_hit = self.nodes[right].bounds.ray_hit(ray_origin, &inv_direction);
+
+                    // Visit the nearest child first so farther ones get culled.
+                    match (left_hit, right_hit) {
+                        (Some(l), Some(r)) if l < r => {
+                            stack.push(right);
+                            stack.push(left);
+                        }
+                        (Some(_), Some(_)) => {
+                            stack.push(left);
+                            stack.push(right);
+                        }
+                        (Some(_), None) => stack.push(left),
+                        (None, Some(_)) => stack.push(right),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+/// Finds the best partition of `entries` according to the Surface Area Heuristic.
+/// The entries are reordered so the split is `entries[..mid]` and `entries[mid..]`.
+/// Returns: The `mid` index or `None` if keeping them all in a leaf is cheaper.
+fn find_sah_split(entries: &mut [BuildEntry], bounds: &BoxBounds) -> Option<usize> {
+    let centroid_bounds = entries
+        .iter()
+        .fold(BoxBounds::empty(), |acc, e| acc.grow(&e.centroid));
+    let extent = centroid_bounds.extent();
+
+    let axis = if extent.x >= extent.y && extent.x >= extent.z {
+        0
+    } else if extent.y >= extent.z {
+        1
+    } else {
+        2
+    };
+
+    let axis_min = centroid_bounds.min[axis];
+    let axis_extent = extent[axis];
+    if axis_extent <= f32::EPSILON {
+        // Every centroid is in the same place, we can't split by position.
+        return None;
+    }
+
+    let bucket_of = |centroid: &Vec3| {
+        let relative = (centroid[axis] - axis_min) / axis_extent;
+        ((relative * SAH_BUCKETS as f32) as usize).min(SAH_BUCKETS - 1)
+    };
+
+    let mut counts = [0usize; SAH_BUCKETS];
+    let mut bucket_bounds = [BoxBounds::empty(); SAH_BUCKETS];
+    for entry in entries.iter() {
+        let bucket = bucket_of(&entry.centroid);
+        counts[bucket] += 1;
+        bucket_bounds[bucket] = bucket_bounds[bucket].union(&entry.bounds);
+    }
+
+    let parent_area = bounds.surface_area();
+    let (best_bucket, best_cost) = (0..SAH_BUCKETS - 1)
+        .map(|split| {
+            let (left_count, left_bounds) = (0..=split).fold((0, BoxBounds::empty()), |acc, i| {
+                (acc.0 + counts[i], acc.1.union(&bucket_bounds[i]))
+            });
+            let (right_count, right_bounds) = (split + 1..SAH_BUCKETS)
+                .fold((0, BoxBounds::empty()), |acc, i| {
+                    (acc.0 + counts[i], acc.1.union(&bucket_bounds[i]))
+                });
+
+            let cost = TRAVERSAL_COST
+                + (left_count as f32 * left_bounds.surface_area()
+                    + right_count as f32 * right_bounds.surface_area())
+                    / parent_area;
+
+            (split, cost)
+        })
+        .fold((0, f32::INFINITY), |best, current| {
+            if current.1 < best.1 {
+                current
+            } else {
+                best
+            }
+        });
+
+    if best_cost >= entries.len() as f32 {
+        return None;
+    }
+
+    // Partition in place, everything in the buckets up to `best_bucket` goes to the left.
+    let mut mid = 0;
+    for i in 0..entries.len() {
+        if bucket_of(&entries[i].centroid) <= best_bucket {
+            entries.swap(i, mid);
+            mid += 1;
+        }
+    }
+
+    if mid == 0 || mid == entries.len() {
+        None
+    } else {
+        Some(mid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cube::Cube, material::RUBBER};
+
+    fn cube_row(count: usize) -> Vec<Box<dyn Traceable>> {
+        (0..count)
+            .map(|i| {
+                Box::new(Cube::new(
+                    i as u32,
+                    Vec3::new(i as f32 * 2.0, 0.0, 0.0),
+                    1.0,
+                    RUBBER,
+                    Vec3::new(0.0, 1.0, 0.0),
+                )) as Box<dyn Traceable>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let objects = cube_row(100);
+        let bvh = Bvh::new(&objects);
+        let origin = Vec3::new(-5.0, 0.1, 0.2);
+
+        for target in [0.0, 31.0, 77.5, 198.0, 250.0] {
+            let direction = (Vec3::new(target, 0.0, 0.0) - origin).normalize();
+
+            let expected = objects
+                .iter()
+                .filter_map(|o| o.ray_intersect(&origin, &direction))
+                .map(|i| i.distance)
+                .fold(f32::INFINITY, f32::min);
+            let found = bvh
+                .ray_intersect(&objects, &origin, &direction)
+                .map(|i| i.distance)
+                .unwrap_or(f32::INFINITY);
+
+            assert_eq!(expected, found);
+        }
+    }
+
+    #[test]
+    fn test_every_object_ends_in_a_leaf() {
+        let objects = cube_row(37);
+        let bvh = Bvh::new(&objects);
+
+        let mut indices = bvh.indices.clone();
+        indices.sort();
+
+        assert_eq!(indices, (0..37).collect::<Vec<_>>());
+    }
+}
@@ -1,4 +1,4 @@
-use std::f32::consts::PI;
+use std::f32::consts::{FRAC_PI_2, PI};
 
 use glm::Vec3;
 
@@ -14,6 +14,9 @@ pub struct Camera {
 
     /// What's the up vector of the camera.
     pub up: Vec3,
+
+    /// Vertical field of view in radians.
+    pub fov: f32,
 }
 
 impl Camera {
@@ -22,11 +25,14 @@ impl Camera {
     /// * `eye`: Camera position in the world space.
     /// * `center`: Point the camera is looking at.
     /// * `up`: What's the up vector of the camera.
+    ///
+    /// The camera starts with a vertical field of view of 90°.
     pub fn new(eye: Vec3, center: Vec3, up: Vec3) -> Self {
         Camera {
             eye,
             center,
             up,
+            fov: FRAC_PI_2,
             has_changed: true,
         }
     }
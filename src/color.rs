@@ -1,3 +1,5 @@
+use crate::tonemap::srgb_to_linear;
+
 /// Represents a Color to print in the screen.
 #[derive(Debug, Clone, Copy)]
 pub struct Color {
@@ -148,6 +150,105 @@ impl std::ops::Sub for Color {
     }
 }
 
+/// Linear RGB light intensity used while rendering.
+///
+/// Unlike `Color` it's neither quantized nor clamped, so it can hold values greater than 1.0
+/// and only gets converted into a `Color` once the whole pixel has been computed.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Radiance {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl Radiance {
+    pub const fn new(r: f32, g: f32, b: f32) -> Self {
+        Radiance { r, g, b }
+    }
+
+    pub const fn black() -> Self {
+        Radiance::new(0.0, 0.0, 0.0)
+    }
+
+    /// Computes the relative luminance according to the Rec. 709 primaries.
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    pub fn max_component(&self) -> f32 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    /// Quantizes every channel as is into a `Color`, everything outside of [0, 1] gets clipped.
+    ///
+    /// No transfer function is applied, see `DisplayTransform` for that.
+    pub fn to_color(&self) -> Color {
+        let to_channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        Color::new(to_channel(self.r), to_channel(self.g), to_channel(self.b))
+    }
+}
+
+/// Colors are assumed to be sRGB encoded, so they get decoded into linear radiance.
+impl From<Color> for Radiance {
+    fn from(value: Color) -> Self {
+        let Color { r, g, b } = value;
+        let to_linear = |channel: u8| srgb_to_linear(channel as f32 / 255.0);
+
+        Radiance::new(to_linear(r), to_linear(g), to_linear(b))
+    }
+}
+
+impl From<&Color> for Radiance {
+    fn from(value: &Color) -> Self {
+        Radiance::from(*value)
+    }
+}
+
+impl std::ops::Add for Radiance {
+    type Output = Radiance;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Radiance::new(self.r + rhs.r, self.g + rhs.g, self.b + rhs.b)
+    }
+}
+
+impl std::ops::AddAssign for Radiance {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl std::ops::Mul<f32> for Radiance {
+    type Output = Radiance;
+
+    fn mul(self, factor: f32) -> Self::Output {
+        Radiance::new(self.r * factor, self.g * factor, self.b * factor)
+    }
+}
+
+impl std::ops::Mul for Radiance {
+    type Output = Radiance;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Radiance::new(self.r * rhs.r, self.g * rhs.g, self.b * rhs.b)
+    }
+}
+
+impl std::ops::Div<f32> for Radiance {
+    type Output = Radiance;
+
+    fn div(self, divisor: f32) -> Self::Output {
+        Radiance::new(self.r / divisor, self.g / divisor, self.b / divisor)
+    }
+}
+
+impl std::iter::Sum for Radiance {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Radiance::black(), |acc, r| acc + r)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,4 +276,12 @@ mod tests {
         assert_eq!(g, 255);
         assert_eq!(b, 255);
     }
+
+    #[test]
+    fn test_radiance_is_not_clamped_until_converted() {
+        let radiance = Radiance::from(Color::new(255, 128, 0)) * 3.0 + Radiance::new(0.5, 0.5, 0.5);
+
+        assert!(radiance.r > 1.0);
+        assert_eq!(radiance.to_color(), Color::new(255, 255, 128));
+    }
 }
@@ -1,6 +1,6 @@
 use core::f32;
 
-use glm::{Vec2, Vec3};
+use glm::{Mat3, Vec2, Vec3};
 
 use crate::{
     material::Material,
@@ -9,21 +9,91 @@ use crate::{
     texture::CubeFace,
 };
 
+/// A box that can be rotated in any direction and have a different length on each side.
 #[derive(Debug)]
 pub struct Cube {
     pub id: u32,
     pub center: Vec3,
-    pub size: f32,
+    /// The length of the sides of the box along its own x, y and z axis.
+    pub extents: Vec3,
     pub material: Material,
+    /// The columns are the x, y and z axis of the box in world space.
+    rotation: Mat3,
     bounds: BoxBounds,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct BoxBounds {
     pub min: Vec3,
     pub max: Vec3,
 }
 
+impl BoxBounds {
+    /// Bounds that contain nothing, growing them with anything yields the other thing.
+    pub fn empty() -> Self {
+        BoxBounds {
+            min: Vec3::repeat(f32::INFINITY),
+            max: Vec3::repeat(f32::NEG_INFINITY),
+        }
+    }
+
+    /// Computes the smallest bounds that contain both `self` and `other`.
+    pub fn union(&self, other: &BoxBounds) -> Self {
+        BoxBounds {
+            min: glm::min2(&self.min, &other.min),
+            max: glm::max2(&self.max, &other.max),
+        }
+    }
+
+    /// Computes the smallest bounds that contain both `self` and `point`.
+    pub fn grow(&self, point: &Vec3) -> Self {
+        BoxBounds {
+            min: glm::min2(&self.min, point),
+            max: glm::max2(&self.max, point),
+        }
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn extent(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let extent = self.extent();
+        if extent.x < 0.0 || extent.y < 0.0 || extent.z < 0.0 {
+            return 0.0;
+        }
+
+        2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+    }
+
+    /// Checks if the ray hits the bounds using the slab method.
+    /// Returns: The distance at which the ray enters the bounds,
+    /// zero if the origin is already inside.
+    ///
+    /// * `ray_origin`: The origin of the ray.
+    /// * `inv_direction`: The component-wise inverse of the ray direction.
+    pub fn ray_hit(&self, ray_origin: &Vec3, inv_direction: &Vec3) -> Option<f32> {
+        let t0 = (self.min - ray_origin).component_mul(inv_direction);
+        let t1 = (self.max - ray_origin).component_mul(inv_direction);
+
+        let tmin = glm::min2(&t0, &t1);
+        let tmax = glm::max2(&t0, &t1);
+
+        let tnear = tmin.x.max(tmin.y).max(tmin.z).max(0.0);
+        let tfar = tmax.x.min(tmax.y).min(tmax.z);
+
+        if tnear <= tfar {
+            Some(tnear)
+        } else {
+            None
+        }
+    }
+}
+
 impl Cube {
     /// Creates a new Cube
     ///
@@ -33,36 +103,142 @@ impl Cube {
     /// * `material`: The Material of which is the cube made of.
     /// * `up`: In what direction is up for the cube?
     pub fn new(id: u32, center: Vec3, size: f32, material: Material, up: Vec3) -> Self {
-        let bounds = Cube::compute_bounds(&center, &up, &size);
+        let rotation = Cube::rotation(&up, &Vec3::new(0.0, 0.0, 1.0));
+        Cube::with_rotation(id, center, Vec3::repeat(size), material, rotation)
+    }
+
+    /// Creates a new box with any orientation and size.
+    ///
+    /// * `id`: The Unique identifier of the box object.
+    /// * `center`: The point at the center of the box.
+    /// * `extents`: The length of the sides of the box along its own x, y and z axis.
+    /// * `material`: The Material of which is the box made of.
+    /// * `up`: The direction of the y axis of the box, it's where its `TOP` face looks at.
+    /// * `forward`: The direction the z axis of the box should be the closest to,
+    ///   it's where its `FORWARDS` face looks at.
+    pub fn oriented(
+        id: u32,
+        center: Vec3,
+        extents: Vec3,
+        material: Material,
+        up: Vec3,
+        forward: Vec3,
+    ) -> Self {
+        let rotation = Cube::rotation(&up, &forward);
+        Cube::with_rotation(id, center, extents, material, rotation)
+    }
+
+    fn with_rotation(
+        id: u32,
+        center: Vec3,
+        extents: Vec3,
+        material: Material,
+        rotation: Mat3,
+    ) -> Self {
+        let bounds = oriented_bounds(&center, &rotation, &(extents / 2.0));
 
         Cube {
             id,
             center,
-            size,
-            bounds,
+            extents,
             material,
+            rotation,
+            bounds,
         }
     }
 
-    /// Get's an array that represents the bounds of the cube.
-    /// The index 0 represents the x bounds.
-    /// The index 1 represents the y bounds.
-    /// The index 2 represents the z bounds.
+    /// Builds the rotation whose y axis is `up` and whose z axis is the closest one to `forward`.
+    /// If `forward` is parallel to `up` any perpendicular direction is used instead.
+    fn rotation(up: &Vec3, forward: &Vec3) -> Mat3 {
+        let y = up.normalize();
+        let projected_forward = forward - y * forward.dot(&y);
+        let z = if projected_forward.magnitude() > 1e-4 {
+            projected_forward.normalize()
+        } else {
+            // Any direction perpendicular to `up` will do, the world axis least aligned with it
+            // is never parallel to it
+            let abs_y = y.abs();
+            let helper = if abs_y.x <= abs_y.y && abs_y.x <= abs_y.z {
+                Vec3::new(1.0, 0.0, 0.0)
+            } else if abs_y.y <= abs_y.z {
+                Vec3::new(0.0, 1.0, 0.0)
+            } else {
+                Vec3::new(0.0, 0.0, 1.0)
+            };
+            helper.cross(&y).normalize()
+        };
+        let x = y.cross(&z);
+
+        Mat3::from_columns(&[x, y, z])
+    }
+
+    /// Computes the axis aligned bounds that contain a cube of the given size pointing `up`.
     pub fn compute_bounds(center: &Vec3, up: &Vec3, size: &f32) -> BoxBounds {
-        let half_size = size / 2.0;
+        let rotation = Cube::rotation(up, &Vec3::new(0.0, 0.0, 1.0));
+        oriented_bounds(center, &rotation, &Vec3::repeat(size / 2.0))
+    }
 
-        let min = Vec3::new(
-            center.x - half_size,
-            center.y - half_size,
-            center.z - half_size,
-        );
-        let max = Vec3::new(
-            center.x + half_size,
-            center.y + half_size,
-            center.z + half_size,
-        );
+    /// The x, y and z axis of the box in world space.
+    pub fn axes(&self) -> [Vec3; 3] {
+        [
+            self.rotation.column(0).into(),
+            self.rotation.column(1).into(),
+            self.rotation.column(2).into(),
+        ]
+    }
+}
+
+/// Computes the axis aligned bounds that contain a rotated box.
+///
+/// * `center`: The point at the center of the box.
+/// * `rotation`: The columns are the axis of the box in world space.
+/// * `half_extents`: Half the length of the sides of the box along its own axis.
+fn oriented_bounds(center: &Vec3, rotation: &Mat3, half_extents: &Vec3) -> BoxBounds {
+    let reach = rotation.abs() * half_extents;
+
+    BoxBounds {
+        min: center - reach,
+        max: center + reach,
+    }
+}
 
-        BoxBounds { min, max }
+/// The face of a box that is perpendicular to `axis`, on its positive or negative side.
+fn box_face(axis: usize, positive_side: bool) -> CubeFace {
+    match (axis, positive_side) {
+        (0, false) => CubeFace::LEFT,
+        (0, true) => CubeFace::RIGHT,
+        (1, false) => CubeFace::BOTTOM,
+        (1, true) => CubeFace::TOP,
+        (_, false) => CubeFace::BACKWARDS,
+        (_, true) => CubeFace::FORWARDS,
+    }
+}
+
+/// Computes the normal and texture coordinates of a point that lies on a face of an
+/// axis aligned cube.
+/// Returns: A tuple in the form of (normal, texture_cords).
+///
+/// * `face`: The face of the cube the point is on.
+/// * `point`: The point on the face.
+/// * `min`: The corner of the cube with the smallest coordinates.
+/// * `size`: The length of the sides of the cube along the x, y and z axis.
+pub fn face_attributes(face: &CubeFace, point: &Vec3, min: &Vec3, size: &Vec3) -> (Vec3, Vec2) {
+    let relative = (point - min).component_div(size);
+
+    match face {
+        CubeFace::LEFT => (Vec3::new(-1.0, 0.0, 0.0), Vec2::new(relative.y, relative.z)),
+        CubeFace::RIGHT => (
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec2::new(1.0 - relative.y, relative.z),
+        ),
+        CubeFace::BOTTOM => (Vec3::new(0.0, -1.0, 0.0), Vec2::new(relative.x, relative.z)),
+        CubeFace::TOP => (Vec3::new(0.0, 1.0, 0.0), Vec2::new(relative.x, relative.z)),
+        CubeFace::BACKWARDS => (
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec2::new(relative.x, 1.0 - relative.y),
+        ),
+        CubeFace::FORWARDS => (Vec3::new(0.0, 0.0, 1.0), Vec2::new(relative.x, relative.y)),
+        CubeFace::NONE => (Vec3::zeros(), Vec2::zeros()),
     }
 }
 
@@ -74,146 +250,145 @@ impl PartialEq for Cube {
 impl Eq for Cube {}
 
 impl Traceable for Cube {
+    fn bounding_box(&self) -> BoxBounds {
+        self.bounds
+    }
+
     fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Option<Intersect> {
         // Algorithm base on:
         // https://www.scratchapixel.com/lessons/3d-basic-rendering/minimal-ray-tracer-rendering-simple-shapes/ray-box-intersection.html
-        let cube_bounds = &self.bounds;
-
-        //         println!(
-        //             r#"Checking if ray:
-        // origin: {:?}
-        // direction: {:?}
-        // Collides!"#,
-        //             ray_origin, ray_direction
-        //         );
-
-        let t0x = (cube_bounds.min.x - ray_origin.x) / ray_direction.x;
-        let t1x = (cube_bounds.max.x - ray_origin.x) / ray_direction.x;
+        // The ray is moved into the space of the box, where it's axis aligned and centered at
+        // the origin. Rotations keep lengths so distances are the same in both spaces.
+        let local_origin = self.rotation.tr_mul(&(ray_origin - self.center));
+        let local_direction = self.rotation.tr_mul(ray_direction);
+        let half_extents = self.extents / 2.0;
 
-        let (txmin, txmax) = minmax(t0x, t1x);
-        let (mut tmin, mut tmax) = (txmin, txmax);
+        let mut tmin = f32::NEG_INFINITY;
+        let mut tmax = f32::INFINITY;
+        // The axis of the slab the ray enters and exits last and first respectively.
+        let mut entry_axis = 0;
+        let mut exit_axis = 0;
 
-        let t0y = (cube_bounds.min.y - ray_origin.y) / ray_direction.y;
-        let t1y = (cube_bounds.max.y - ray_origin.y) / ray_direction.y;
+        for axis in 0..3 {
+            let t0 = (-half_extents[axis] - local_origin[axis]) / local_direction[axis];
+            let t1 = (half_extents[axis] - local_origin[axis]) / local_direction[axis];
+            let (taxis_min, taxis_max) = minmax(t0, t1);
 
-        let (tymin, tymax) = minmax(t0y, t1y);
+            if tmin > taxis_max || taxis_min > tmax {
+                return None;
+            }
 
-        if tmin > tymax || tymin > tmax {
-            return None;
+            if taxis_min > tmin {
+                tmin = taxis_min;
+                entry_axis = axis;
+            }
+            if taxis_max < tmax {
+                tmax = taxis_max;
+                exit_axis = axis;
+            }
         }
 
-        tmin = tmin.max(tymin);
-        tmax = tmax.min(tymax);
-
-        let t0z = (cube_bounds.min.z - ray_origin.z) / ray_direction.z;
-        let t1z = (cube_bounds.max.z - ray_origin.z) / ray_direction.z;
-
-        let (tzmin, tzmax) = minmax(t0z, t1z);
-
-        if tmin > tzmax || tzmin > tmax {
-            //             println!(
-            //                 r#"No collision found!
-            // {} > {} || {} > {}
-            // "#,
-            //                 tmin, tzmax, tzmin, tmax
-            //             );
+        // The whole cube is behind the ray.
+        if tmax < 0.0 {
             return None;
         }
 
-        //         println!();
-        //         println!(
-        //             r#"Checking if ray:
-        // origin: {:?}
-        // direction: {:?}
-        // Collides!"#,
-        //             ray_origin, ray_direction
-        //         );
-        //
-        //         println!(
-        //             r#"Collides in X, Y and Z:
-        // X: {}, {}
-        // Y: {}, {}
-        // Z: {}, {}
-        // "#,
-        //             txmin, txmax, tymin, tymax, tzmin, tzmax
-        //         );
-
-        // Both this values are the same...
-        tmin = tmin.max(tzmin);
-        tmax = tmax.min(tzmax);
-
-        let distance = if tmin < 0.0 { tmax } else { tmin };
-        let point = ray_origin + ray_direction * distance;
+        // Rays that start inside the cube hit the face they leave through.
+        let (distance, face) = if tmin < 0.0 {
+            let positive_side = local_direction[exit_axis] > 0.0;
+            (tmax, box_face(exit_axis, positive_side))
+        } else {
+            let positive_side = local_direction[entry_axis] < 0.0;
+            (tmin, box_face(entry_axis, positive_side))
+        };
 
-        let mut normal = Vec3::zeros();
-        let mut face = CubeFace::NONE;
-        let mut texture_cords = Vec2::zeros();
-
-        let limit = 1e-3;
-        if (point.x - cube_bounds.min.x).abs() < limit {
-            normal = Vec3::new(-1.0, 0.0, 0.0);
-            face = CubeFace::LEFT;
-            texture_cords = Vec2::new(
-                (point.y - cube_bounds.min.y) / self.size,
-                (point.z - cube_bounds.min.z) / self.size,
-            );
-        } else if (point.x - cube_bounds.max.x).abs() < limit {
-            normal = Vec3::new(1.0, 0.0, 0.0);
-            face = CubeFace::RIGHT;
-            texture_cords = Vec2::new(
-                1.0 - (point.y - cube_bounds.min.y) / self.size,
-                (point.z - cube_bounds.min.z) / self.size,
-            );
-        } else if (point.y - cube_bounds.min.y).abs() < limit {
-            normal = Vec3::new(0.0, -1.0, 0.0);
-            face = CubeFace::BOTTOM;
-            texture_cords = Vec2::new(
-                (point.x - cube_bounds.min.x) / self.size,
-                (point.z - cube_bounds.min.z) / self.size,
-            );
-        } else if (point.y - cube_bounds.max.y).abs() < limit {
-            normal = Vec3::new(0.0, 1.0, 0.0);
-            face = CubeFace::TOP;
-            texture_cords = Vec2::new(
-                (point.x - cube_bounds.min.x) / self.size,
-                (point.z - cube_bounds.min.z) / self.size,
-            );
-        } else if (point.z - cube_bounds.min.z).abs() < limit {
-            normal = Vec3::new(0.0, 0.0, -1.0);
-            face = CubeFace::BACKWARDS;
-            texture_cords = Vec2::new(
-                (point.x - cube_bounds.min.x) / self.size,
-                1.0 - (point.y - cube_bounds.min.y) / self.size,
-            );
-        } else if (point.z - cube_bounds.max.z).abs() < limit {
-            normal = Vec3::new(0.0, 0.0, 1.0);
-            face = CubeFace::FORWARDS;
-            texture_cords = Vec2::new(
-                (point.x - cube_bounds.min.x) / self.size,
-                (point.y - cube_bounds.min.y) / self.size,
-            );
-        }
+        let point = ray_origin + ray_direction * distance;
+        let local_point = local_origin + local_direction * distance;
+        let (local_normal, texture_cords) =
+            face_attributes(&face, &local_point, &-half_extents, &self.extents);
 
         let intersect = Intersect {
             distance,
             point,
-            normal,
+            normal: self.rotation * local_normal,
             material: self.material.clone(),
             texture_cords,
             face,
         };
 
-        //         println!();
-        //         println!(
-        //             r#"Found collisions:
-        // x: ({}, {})
-        // y: ({}, {})
-        // z: ({}, {})
-        // Which generate the Point: {:?}"#,
-        //             txmin, txmax, tymin, tymax, tzmin, tzmax, point
-        //         );
-        //         println!("Found an intersection! {:#?}", intersect);
-
         Some(intersect)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::STONE;
+
+    #[test]
+    fn test_tilted_cube_hits_its_faces_in_local_space() {
+        // Tilted 45 degrees around z, so the corner between TOP and RIGHT faces points at +x.
+        let up = Vec3::new(-1.0, 1.0, 0.0);
+        let cube = Cube::new(0, Vec3::zeros(), 2.0, STONE, up);
+        let half_diagonal = 2f32.sqrt();
+
+        let bounds = cube.bounding_box();
+        assert!((bounds.max.x - half_diagonal).abs() < 1e-4);
+        assert!((bounds.max.z - 1.0).abs() < 1e-4);
+
+        let origin = Vec3::new(5.0, 0.1, 0.3);
+        let intersect = cube
+            .ray_intersect(&origin, &Vec3::new(-1.0, 0.0, 0.0))
+            .unwrap();
+
+        assert_eq!(intersect.face, CubeFace::RIGHT);
+        let expected_normal = Vec3::new(1.0, 1.0, 0.0).normalize();
+        assert!((intersect.normal - expected_normal).magnitude() < 1e-4);
+        assert!((intersect.texture_cords.y - 0.65).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_upright_cube_keeps_its_faces_axis_aligned() {
+        // Built like the blocks of `world.rs`
+        let cube = Cube::new(0, Vec3::zeros(), 1.0, STONE, Vec3::new(0.0, 1.0, 0.0));
+
+        let intersect = cube
+            .ray_intersect(&Vec3::new(0.1, 0.2, 5.0), &Vec3::new(0.0, 0.0, -1.0))
+            .unwrap();
+        assert_eq!(intersect.face, CubeFace::FORWARDS);
+        assert!((intersect.normal - Vec3::new(0.0, 0.0, 1.0)).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn test_up_parallel_to_forward_still_gives_a_rotation() {
+        for up in [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)] {
+            let rotation = Cube::rotation(&up, &up);
+            assert!(rotation.iter().all(|c| c.is_finite()));
+            assert!((rotation.column(1) - up).magnitude() < 1e-4);
+            assert!((rotation.determinant() - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_oriented_box_with_different_extents() {
+        let cube = Cube::oriented(
+            0,
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(4.0, 1.0, 2.0),
+            STONE,
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+
+        // The local z axis points to +x, so the 2 units long side lies along x.
+        let bounds = cube.bounding_box();
+        assert!((bounds.extent() - Vec3::new(2.0, 1.0, 4.0)).magnitude() < 1e-4);
+
+        let intersect = cube
+            .ray_intersect(&Vec3::new(10.0, 0.0, 0.0), &Vec3::new(-1.0, 0.0, 0.0))
+            .unwrap();
+        assert_eq!(intersect.face, CubeFace::FORWARDS);
+        assert!((intersect.distance - 8.0).abs() < 1e-4);
+        assert!((intersect.normal - Vec3::new(1.0, 0.0, 0.0)).magnitude() < 1e-4);
+    }
+}
@@ -219,4 +219,22 @@ impl Framebuffer {
 
         write_bmp_file(file_path, buffer, *width, *height)
     }
+
+    /// Saves the pixel data into an image located in the given `file_path`.
+    /// The format is chosen according to the extension of the file (png, jpg, etc).
+    pub fn save_image(&self, file_path: &str) -> image::ImageResult<()> {
+        let Framebuffer {
+            width,
+            height,
+            buffer,
+            ..
+        } = self;
+
+        let image = image::RgbImage::from_fn(*width as u32, *height as u32, |x, y| {
+            let Color { r, g, b } = buffer[y as usize * *width + x as usize].into();
+            image::Rgb([r, g, b])
+        });
+
+        image.save(file_path)
+    }
 }
@@ -0,0 +1,561 @@
+//! Imports glTF 2.0 scenes (`.gltf` or `.glb`) as a `Model`.
+//!
+//! Every mesh of the default scene is placed with the transforms of its node hierarchy.
+//! The rest of the file is mapped like this:
+//!
+//! * PBR metallic-roughness materials: the base color factor is the `diffuse` color and the
+//!   base color texture becomes the `texture_map` of the material. Smooth metals reflect
+//!   (`reflectivity` is `metallic * (1 - roughness)`) and the roughness sets how wide the
+//!   highlights are. The emissive factor (and `KHR_materials_emissive_strength`) becomes the
+//!   emission, `KHR_materials_transmission` and blended alpha the transparency and
+//!   `KHR_materials_ior` the refractive index.
+//! * The first perspective camera found is the camera of the model, with its vertical field
+//!   of view. Without one the camera looks at the whole scene from the front.
+//! * `KHR_lights_punctual` lights become point lights. Spot lights shine in every direction
+//!   and directional lights are placed far away in the direction the light comes from.
+//!   Lights in this renderer don't get dimmer with the distance, so `intensity` is copied as is,
+//!   exporters that write physical units need it scaled down to values around 1.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use glm::{Mat3, Mat4, Vec2, Vec3, Vec4};
+use gltf::{
+    camera::Projection, image::Format, khr_lights_punctual::Kind, material::AlphaMode, Document,
+    Node,
+};
+
+use crate::{
+    camera::Camera,
+    color::Color,
+    light::Light,
+    material::Material,
+    mesh::{Mesh, Triangle},
+    raytracer::Traceable,
+    scene::Scene,
+    scene_file::default_ambient_light,
+    texture::{Texture, TextureRegistry},
+    tonemap::linear_to_srgb,
+    Model, TimeOfDay,
+};
+
+/// How far away from the scene directional lights are placed.
+const DIRECTIONAL_LIGHT_DISTANCE: f32 = 1000.0;
+
+#[derive(Debug)]
+pub enum GltfError {
+    Import(PathBuf, gltf::Error),
+    NoScene(PathBuf),
+    /// A mesh of the file uses a vertex its attributes don't have.
+    InvalidIndex {
+        path: PathBuf,
+        mesh: usize,
+        index: usize,
+        vertices: usize,
+    },
+}
+
+impl Display for GltfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GltfError::Import(path, error) => {
+                write!(f, "Couldn't import glTF file {}: {error}", path.display())
+            }
+            GltfError::NoScene(path) => {
+                write!(f, "The glTF file {} doesn't have any scene", path.display())
+            }
+            GltfError::InvalidIndex {
+                path,
+                mesh,
+                index,
+                vertices,
+            } => write!(
+                f,
+                "Mesh {mesh} of glTF file {} uses vertex {index} but only has {vertices} vertices",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+/// Everything taken from a glTF file, before it's turned into a `Model`.
+struct ImportedScene {
+    scene: Scene,
+    camera: Option<Camera>,
+    lights: Vec<Light>,
+}
+
+/// Converts a linear color of a glTF file to a `Color`.
+fn to_color(rgb: [f32; 3]) -> Color {
+    let channel = |c: f32| (linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0).round() as u8;
+    Color::new(channel(rgb[0]), channel(rgb[1]), channel(rgb[2]))
+}
+
+fn transform_point(transform: &Mat4, point: &Vec3) -> Vec3 {
+    (transform * Vec4::new(point.x, point.y, point.z, 1.0)).xyz()
+}
+
+fn transform_direction(transform: &Mat4, direction: &Vec3) -> Vec3 {
+    (transform * Vec4::new(direction.x, direction.y, direction.z, 0.0)).xyz()
+}
+
+/// Converts an image of the file to a `Texture`, only 8 bit formats are supported.
+fn texture_from_image(image: &gltf::image::Data) -> Option<Texture> {
+    let channels = match image.format {
+        Format::R8 => 1,
+        Format::R8G8 => 2,
+        Format::R8G8B8 => 3,
+        Format::R8G8B8A8 => 4,
+        _ => return None,
+    };
+
+    let colors = image
+        .pixels
+        .chunks_exact(channels)
+        .map(|pixel| match pixel {
+            [gray] | [gray, _] => Color::new(*gray, *gray, *gray),
+            [r, g, b, ..] => Color::new(*r, *g, *b),
+            _ => unreachable!(),
+        })
+        .collect();
+
+    Some(Texture::from_colors(image.width, image.height, colors))
+}
+
+/// Converts a glTF material to a `Material`, `texture_map` being its base color texture.
+fn convert_material(gltf_material: &gltf::Material, texture_map: Option<Arc<Texture>>) -> Material {
+    let pbr = gltf_material.pbr_metallic_roughness();
+    let [r, g, b, alpha] = pbr.base_color_factor();
+    let metallic = pbr.metallic_factor().clamp(0.0, 1.0);
+    let roughness = pbr.roughness_factor().clamp(0.0, 1.0);
+
+    // Dielectrics reflect about 4% of the light, metals their whole color.
+    let highlights = (0.04 + 0.96 * metallic) * (1.0 - roughness);
+    // Roughly the Phong exponent with the same highlight width as the GGX roughness.
+    let alpha_roughness = (roughness * roughness).max(1e-3);
+    let specular = (2.0 / (alpha_roughness * alpha_roughness) - 2.0).clamp(1.0, 1000.0);
+
+    let transmission = gltf_material
+        .transmission()
+        .map_or(0.0, |transmission| transmission.transmission_factor());
+    let blend_transparency = match gltf_material.alpha_mode() {
+        AlphaMode::Blend => 1.0 - alpha,
+        _ => 0.0,
+    };
+    let transparency = transmission.max(blend_transparency).clamp(0.0, 1.0);
+
+    let [er, eg, eb] = gltf_material.emissive_factor();
+    let emission = er.max(eg).max(eb) * gltf_material.emissive_strength().unwrap_or(1.0);
+    let emission_color = (emission > 0.0).then(|| {
+        let max = er.max(eg).max(eb);
+        to_color([er / max, eg / max, eb / max])
+    });
+
+    Material {
+        diffuse: to_color([r, g, b]),
+        specular,
+        albedo: (1.0 - highlights, highlights),
+        reflectivity: (metallic * (1.0 - roughness)).min(1.0 - transparency),
+        transparency,
+        refractive_index: gltf_material.ior().unwrap_or(1.5),
+        emission,
+        emission_color,
+        texture_map,
+        texture: None,
+    }
+}
+
+/// Walks the node hierarchy of a glTF document, collecting everything it can show.
+struct Importer<'a> {
+    file_path: &'a Path,
+    buffers: &'a [gltf::buffer::Data],
+    images: &'a [gltf::image::Data],
+    textures: HashMap<usize, Option<Arc<Texture>>>,
+    materials: HashMap<Option<usize>, Material>,
+    imported: ImportedScene,
+    /// Directional lights wait until the size of the scene is known.
+    directional_lights: Vec<(Vec3, Light)>,
+}
+
+impl<'a> Importer<'a> {
+    fn new(
+        file_path: &'a Path,
+        buffers: &'a [gltf::buffer::Data],
+        images: &'a [gltf::image::Data],
+    ) -> Self {
+        Importer {
+            file_path,
+            buffers,
+            images,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            imported: ImportedScene {
+                scene: Scene::default(),
+                camera: None,
+                lights: vec![],
+            },
+            directional_lights: vec![],
+        }
+    }
+
+    fn texture(&mut self, image_index: usize) -> Option<Arc<Texture>> {
+        let images = self.images;
+        self.textures
+            .entry(image_index)
+            .or_insert_with(|| {
+                let texture = images.get(image_index).and_then(texture_from_image);
+                if texture.is_none() {
+                    eprintln!(
+                        "Warning: the format of glTF image {image_index} isn't supported, using the base color instead"
+                    );
+                }
+                texture.map(Arc::new)
+            })
+            .clone()
+    }
+
+    fn material(&mut self, gltf_material: &gltf::Material) -> Material {
+        if let Some(material) = self.materials.get(&gltf_material.index()) {
+            return material.clone();
+        }
+
+        let texture_map = gltf_material
+            .pbr_metallic_roughness()
+            .base_color_texture()
+            .and_then(|info| self.texture(info.texture().source().index()));
+        let material = convert_material(gltf_material, texture_map);
+        self.materials
+            .insert(gltf_material.index(), material.clone());
+        material
+    }
+
+    fn visit(&mut self, node: &Node, parent_transform: &Mat4) -> Result<(), GltfError> {
+        let transform = parent_transform * Mat4::from(node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            let mesh = self.mesh(&mesh, &transform)?;
+            if !mesh.is_empty() {
+                self.imported.scene.push(mesh);
+            }
+        }
+        if let Some(camera) = node.camera() {
+            self.camera(&camera, &transform);
+        }
+        if let Some(light) = node.light() {
+            self.light(&light, &transform);
+        }
+
+        for child in node.children() {
+            self.visit(&child, &transform)?;
+        }
+        Ok(())
+    }
+
+    fn mesh(&mut self, mesh: &gltf::Mesh, transform: &Mat4) -> Result<Mesh, GltfError> {
+        let normal_transform = glm::mat4_to_mat3(transform)
+            .try_inverse()
+            .unwrap_or_else(Mat3::identity)
+            .transpose();
+        let buffers = self.buffers;
+        let mut triangles = vec![];
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                eprintln!(
+                    "Warning: skipping a primitive of mesh {} that isn't made of triangles",
+                    mesh.index()
+                );
+                continue;
+            }
+
+            let gltf_material = primitive.material();
+            let material = self.material(&gltf_material);
+            let tex_coord_set = gltf_material
+                .pbr_metallic_roughness()
+                .base_color_texture()
+                .map_or(0, |info| info.tex_coord());
+
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+            let positions: Vec<Vec3> = positions
+                .map(|p| transform_point(transform, &Vec3::from(p)))
+                .collect();
+            let normals: Option<Vec<Vec3>> = reader.read_normals().map(|normals| {
+                normals
+                    .map(|n| (normal_transform * Vec3::from(n)).normalize())
+                    .collect()
+            });
+            // glTF textures start at the top, `Triangle` expects UVs starting at the bottom.
+            let uvs: Option<Vec<Vec2>> = reader
+                .read_tex_coords(tex_coord_set)
+                .map(|uvs| uvs.into_f32().map(|[u, v]| Vec2::new(u, 1.0 - v)).collect());
+            let indices: Vec<usize> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+                None => (0..positions.len()).collect(),
+            };
+
+            // Every index must point to a vertex that has all of the attributes.
+            let vertices = [
+                Some(positions.len()),
+                normals.as_ref().map(Vec::len),
+                uvs.as_ref().map(Vec::len),
+            ]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(0);
+            if let Some(&index) = indices.iter().find(|&&i| i >= vertices) {
+                return Err(GltfError::InvalidIndex {
+                    path: self.file_path.to_path_buf(),
+                    mesh: mesh.index(),
+                    index,
+                    vertices,
+                });
+            }
+
+            for face in indices.chunks_exact(3) {
+                let face = [face[0], face[1], face[2]];
+                triangles.push(Triangle {
+                    vertices: face.map(|i| positions[i]),
+                    normals: normals.as_ref().map(|normals| face.map(|i| normals[i])),
+                    uvs: uvs.as_ref().map(|uvs| face.map(|i| uvs[i])),
+                    material: material.clone(),
+                });
+            }
+        }
+
+        Ok(Mesh::new(triangles))
+    }
+
+    fn camera(&mut self, camera: &gltf::Camera, transform: &Mat4) {
+        if self.imported.camera.is_some() {
+            return;
+        }
+        let Projection::Perspective(perspective) = camera.projection() else {
+            eprintln!("Warning: orthographic cameras aren't supported, ignoring it");
+            return;
+        };
+
+        // glTF cameras look towards -Z with +Y up.
+        let eye = transform_point(transform, &Vec3::zeros());
+        let forward = transform_direction(transform, &Vec3::new(0.0, 0.0, -1.0)).normalize();
+        let up = transform_direction(transform, &Vec3::new(0.0, 1.0, 0.0)).normalize();
+
+        let mut camera = Camera::new(eye, eye + forward, up);
+        camera.fov = perspective.yfov();
+        self.imported.camera = Some(camera);
+    }
+
+    fn light(&mut self, light: &gltf::khr_lights_punctual::Light, transform: &Mat4) {
+        let converted = Light {
+            position: transform_point(transform, &Vec3::zeros()),
+            color: to_color(light.color()),
+            intensity: light.intensity(),
+        };
+
+        match light.kind() {
+            Kind::Directional => {
+                let direction =
+                    transform_direction(transform, &Vec3::new(0.0, 0.0, -1.0)).normalize();
+                self.directional_lights.push((direction, converted));
+            }
+            Kind::Point | Kind::Spot { .. } => self.imported.lights.push(converted),
+        }
+    }
+
+    fn finish(mut self) -> ImportedScene {
+        let center = if self.imported.scene.is_empty() {
+            Vec3::zeros()
+        } else {
+            self.imported.scene.bounding_box().centroid()
+        };
+
+        for (direction, mut light) in self.directional_lights {
+            light.position = center - direction * DIRECTIONAL_LIGHT_DISTANCE;
+            self.imported.lights.push(light);
+        }
+
+        self.imported
+    }
+}
+
+/// Collects the meshes, the first camera and the lights of the default scene of `document`.
+fn import_scene(
+    file_path: &Path,
+    document: &Document,
+    buffers: &[gltf::buffer::Data],
+    images: &[gltf::image::Data],
+) -> Result<ImportedScene, GltfError> {
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| GltfError::NoScene(file_path.to_path_buf()))?;
+
+    let mut importer = Importer::new(file_path, buffers, images);
+    for node in scene.nodes() {
+        importer.visit(&node, &Mat4::identity())?;
+    }
+
+    Ok(importer.finish())
+}
+
+/// A camera that looks at the whole scene from the front.
+fn framing_camera(scene: &Scene) -> Camera {
+    if scene.is_empty() {
+        return Camera::new(
+            Vec3::new(0.0, 0.0, 10.0),
+            Vec3::zeros(),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+    }
+
+    let bounds = scene.bounding_box();
+    let center = bounds.centroid();
+    let distance = bounds.extent().magnitude().max(1.0);
+    let eye = center + Vec3::new(0.0, 0.25, 1.0).normalize() * distance;
+    Camera::new(eye, center, Vec3::new(0.0, 1.0, 0.0))
+}
+
+/// Reads the glTF (or binary glTF) file at `file_path` and builds a `Model` out of its default scene.
+pub fn load_gltf(
+    file_path: impl AsRef<Path>,
+    textures: TextureRegistry,
+) -> Result<Model, GltfError> {
+    let file_path = file_path.as_ref();
+    let (document, buffers, images) =
+        gltf::import(file_path).map_err(|e| GltfError::Import(file_path.to_path_buf(), e))?;
+
+    let ImportedScene {
+        scene,
+        camera,
+        lights,
+    } = import_scene(file_path, &document, &buffers, &images)?;
+
+    let camera = camera.unwrap_or_else(|| framing_camera(&scene));
+    let daytime = TimeOfDay::Day;
+
+    Ok(Model {
+        scene,
+        lights,
+        ambient_light: default_ambient_light(),
+        camera,
+        textures,
+        sky_color: daytime.sky_color(),
+        top_light: daytime.top_light(),
+        daytime,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A red metallic triangle on the XY plane, a camera looking at it and a point light,
+    /// all of them moved 1 unit up by their parent node.
+    const TRIANGLE_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": {
+            "KHR_lights_punctual": {
+                "lights": [{ "type": "point", "color": [1, 1, 1], "intensity": 0.5 }]
+            }
+        },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "translation": [0, 1, 0], "children": [1, 2, 3] },
+            { "mesh": 0 },
+            { "camera": 0, "translation": [0, 0, 5] },
+            { "translation": [1, 2, 3], "extensions": { "KHR_lights_punctual": { "light": 0 } } }
+        ],
+        "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.8, "znear": 0.1 } }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+        "materials": [{
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [1, 0, 0, 1], "metallicFactor": 1, "roughnessFactor": 0
+            }
+        }],
+        "buffers": [{
+            "byteLength": 36,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAAAAQAAAAAAAAAAAAAAAAAAAAEAAAAAA"
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "accessors": [{
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0, 0, 0], "max": [2, 2, 0]
+        }]
+    }"#;
+
+    #[test]
+    fn test_imports_nodes_cameras_lights_and_materials() {
+        let (document, buffers, images) = gltf::import_slice(TRIANGLE_GLTF.as_bytes()).unwrap();
+        let imported =
+            import_scene(Path::new("triangle.gltf"), &document, &buffers, &images).unwrap();
+
+        let camera = imported.camera.unwrap();
+        assert!((camera.eye - Vec3::new(0.0, 1.0, 5.0)).magnitude() < 1e-5);
+        assert!((camera.direction() - Vec3::new(0.0, 0.0, -1.0)).magnitude() < 1e-5);
+        assert_eq!(camera.fov, 0.8);
+
+        assert_eq!(imported.lights.len(), 1);
+        assert!((imported.lights[0].position - Vec3::new(1.0, 3.0, 3.0)).magnitude() < 1e-5);
+        assert_eq!(imported.lights[0].intensity, 0.5);
+
+        let intersect = imported
+            .scene
+            .ray_intersect(&Vec3::new(0.5, 1.5, 5.0), &Vec3::new(0.0, 0.0, -1.0))
+            .unwrap();
+        assert!((intersect.distance - 5.0).abs() < 1e-5);
+        assert_eq!(intersect.material.diffuse, Color::new(255, 0, 0));
+        assert_eq!(intersect.material.reflectivity, 1.0);
+    }
+
+    #[test]
+    fn test_rejects_indices_past_the_last_vertex() {
+        // The same triangle, but its third index points to a vertex that doesn't exist.
+        let gltf = TRIANGLE_GLTF
+            .replace(
+                r#""attributes": { "POSITION": 0 }"#,
+                r#""attributes": { "POSITION": 0 }, "indices": 1"#,
+            )
+            .replace(
+                r#""bufferViews": [{ "buffer": 0, "byteLength": 36 }]"#,
+                r#""bufferViews": [
+                    { "buffer": 0, "byteLength": 36 },
+                    { "buffer": 1, "byteLength": 6 }
+                ]"#,
+            )
+            .replace(
+                r#"AEAAAAAA"
+        }],"#,
+                r#"AEAAAAAA"
+        }, { "byteLength": 8, "uri": "data:application/octet-stream;base64,AAABAAUAAAA=" }],"#,
+            )
+            .replace(
+                r#""max": [2, 2, 0]
+        }]"#,
+                r#""max": [2, 2, 0]
+        }, { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }]"#,
+            );
+        let (document, buffers, images) = gltf::import_slice(gltf.as_bytes()).unwrap();
+
+        let error = import_scene(Path::new("broken.gltf"), &document, &buffers, &images);
+
+        assert!(matches!(
+            error,
+            Err(GltfError::InvalidIndex {
+                index: 5,
+                vertices: 3,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,290 @@
+//! Builds terrain out of grayscale heightmap images.
+//!
+//! Every pixel of the heightmap becomes a column of blocks as tall as the pixel is bright.
+//! The top of each column gets a material picked by its height and slope, or by the color
+//! of the same spot of an optional color map.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use glm::Vec3;
+use image::{DynamicImage, GenericImageView, ImageError, ImageReader};
+
+use crate::{
+    color::Color,
+    material::{Material, DIRT, MOSS, STONE, WATER},
+    voxel::VoxelGrid,
+};
+
+#[derive(Debug)]
+pub enum HeightmapError {
+    Image(PathBuf, ImageError),
+}
+
+impl Display for HeightmapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeightmapError::Image(path, error) => {
+                write!(f, "Couldn't read the image {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for HeightmapError {}
+
+/// The material of the top of the columns up to a height and a slope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialBand {
+    /// Highest columns that get the material, as a fraction of the tallest possible column.
+    /// `None` accepts any height.
+    pub max_height: Option<f32>,
+    /// Steepest columns that get the material, in blocks of difference with their neighbors.
+    /// `None` accepts any slope.
+    pub max_slope: Option<usize>,
+    pub material: Material,
+}
+
+impl MaterialBand {
+    fn matches(&self, height: f32, slope: usize) -> bool {
+        self.max_height.is_none_or(|max| height <= max)
+            && self.max_slope.is_none_or(|max| slope <= max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightmapSettings {
+    /// Height in blocks of the columns of white pixels, black ones are a single block tall.
+    pub height: usize,
+    /// Bands checked in order for the top of every column, the first one that matches wins.
+    pub bands: Vec<MaterialBand>,
+    /// How many blocks at the top of a column get the material of its band.
+    pub surface_depth: usize,
+    /// Material under the surface and of the columns no band matches.
+    pub ground: Material,
+    /// Empty cells under this height are filled with water, 0 leaves no water.
+    pub sea_level: usize,
+    /// Materials of the colors of the color map, every column takes the closest color.
+    pub colors: Vec<(Color, Material)>,
+}
+
+impl Default for HeightmapSettings {
+    fn default() -> Self {
+        HeightmapSettings {
+            height: 32,
+            bands: vec![
+                MaterialBand {
+                    max_height: Some(0.15),
+                    max_slope: None,
+                    material: DIRT,
+                },
+                MaterialBand {
+                    max_height: Some(0.75),
+                    max_slope: Some(2),
+                    material: MOSS,
+                },
+            ],
+            surface_depth: 1,
+            ground: STONE,
+            sea_level: 0,
+            colors: vec![],
+        }
+    }
+}
+
+impl HeightmapSettings {
+    /// The material of the closest color to `color`, if there are any colors.
+    fn color_material(&self, color: Color) -> Option<&Material> {
+        let distance = |other: &Color| {
+            [
+                color.r as i32 - other.r as i32,
+                color.g as i32 - other.g as i32,
+                color.b as i32 - other.b as i32,
+            ]
+            .iter()
+            .map(|d| d * d)
+            .sum::<i32>()
+        };
+
+        self.colors
+            .iter()
+            .min_by_key(|(other, _)| distance(other))
+            .map(|(_, material)| material)
+    }
+}
+
+/// Builds the terrain of a heightmap, the pixel `(x, y)` becomes the column at `(x, z = y)`
+/// and the color map (if any) is stretched over the heightmap.
+pub fn heightmap_terrain(
+    heightmap: &DynamicImage,
+    color_map: Option<&DynamicImage>,
+    origin: Vec3,
+    block_size: f32,
+    settings: &HeightmapSettings,
+) -> VoxelGrid {
+    let brightness = heightmap.to_luma16();
+    let (width, depth) = (brightness.width() as usize, brightness.height() as usize);
+    let tallest = settings.height.max(1);
+    let heights: Vec<usize> = brightness
+        .pixels()
+        .map(|pixel| {
+            1 + (pixel.0[0] as f32 / u16::MAX as f32 * (tallest - 1) as f32).round() as usize
+        })
+        .collect();
+    let height_at = |x: usize, z: usize| heights[z * width + x];
+
+    let mut grid = VoxelGrid::new(
+        origin,
+        [width, tallest.max(settings.sea_level), depth],
+        block_size,
+    );
+    let ground = grid.register_material(settings.ground.clone());
+    let water = grid.register_material(WATER);
+
+    for z in 0..depth {
+        for x in 0..width {
+            let height = height_at(x, z);
+            let neighbors = [
+                (x.wrapping_sub(1), z),
+                (x + 1, z),
+                (x, z.wrapping_sub(1)),
+                (x, z + 1),
+            ];
+            let slope = neighbors
+                .into_iter()
+                .filter(|&(nx, nz)| nx < width && nz < depth)
+                .map(|(nx, nz)| height.abs_diff(height_at(nx, nz)))
+                .max()
+                .unwrap_or(0);
+
+            let color = color_map.map(|color_map| {
+                let cx = x as u32 * color_map.width() / width as u32;
+                let cz = z as u32 * color_map.height() / depth as u32;
+                let [r, g, b, _] = color_map.get_pixel(cx, cz).0;
+                Color::new(r, g, b)
+            });
+            let surface = match color.and_then(|color| settings.color_material(color)) {
+                Some(material) => Some(material.clone()),
+                None => {
+                    let relative_height = (height - 1) as f32 / (tallest - 1).max(1) as f32;
+                    settings
+                        .bands
+                        .iter()
+                        .find(|band| band.matches(relative_height, slope))
+                        .map(|band| band.material.clone())
+                }
+            };
+            let surface = surface.map(|material| grid.register_material(material));
+
+            for y in 0..height {
+                let block = match surface {
+                    Some(surface) if y + settings.surface_depth >= height => surface,
+                    _ => ground,
+                };
+                grid.set_block_id(x, y, z, Some(block));
+            }
+            for y in height..settings.sea_level {
+                grid.set_block_id(x, y, z, Some(water));
+            }
+        }
+    }
+
+    grid
+}
+
+fn read_image(path: &Path) -> Result<DynamicImage, HeightmapError> {
+    let to_error = |error| HeightmapError::Image(path.to_path_buf(), error);
+    ImageReader::open(path)
+        .map_err(|error| to_error(ImageError::IoError(error)))?
+        .decode()
+        .map_err(to_error)
+}
+
+/// Reads a heightmap image, and its color map if given, and builds its terrain.
+pub fn load_heightmap(
+    path: impl AsRef<Path>,
+    color_map: Option<&Path>,
+    origin: Vec3,
+    block_size: f32,
+    settings: &HeightmapSettings,
+) -> Result<VoxelGrid, HeightmapError> {
+    let heightmap = read_image(path.as_ref())?;
+    let color_map = color_map.map(read_image).transpose()?;
+
+    Ok(heightmap_terrain(
+        &heightmap,
+        color_map.as_ref(),
+        origin,
+        block_size,
+        settings,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{GOLD, OBSIDIAN};
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+
+    #[test]
+    fn test_brightness_gives_height_and_bands_pick_materials() {
+        // A flat black row next to a white column
+        let heightmap = GrayImage::from_fn(3, 1, |x, _| match x {
+            2 => Luma([255]),
+            _ => Luma([0]),
+        });
+        let settings = HeightmapSettings {
+            height: 5,
+            bands: vec![
+                MaterialBand {
+                    max_height: None,
+                    max_slope: Some(0),
+                    material: MOSS,
+                },
+                MaterialBand {
+                    max_height: Some(0.5),
+                    max_slope: None,
+                    material: DIRT,
+                },
+            ],
+            sea_level: 2,
+            ..HeightmapSettings::default()
+        };
+        let grid = heightmap_terrain(&heightmap.into(), None, Vec3::zeros(), 1.0, &settings);
+
+        assert_eq!(grid.get_block(0, 0, 0), Some(&MOSS));
+        assert_eq!(grid.get_block(0, 1, 0), Some(&WATER));
+        assert_eq!(grid.get_block(1, 0, 0), Some(&DIRT));
+        assert_eq!(grid.get_block(2, 4, 0), Some(&STONE));
+        assert_eq!(grid.get_block(2, 3, 0), Some(&STONE));
+    }
+
+    #[test]
+    fn test_color_map_overrides_bands() {
+        let heightmap = GrayImage::from_pixel(2, 2, Luma([128]));
+        let color_map = RgbImage::from_fn(1, 2, |_, y| match y {
+            0 => Rgb([250, 200, 10]),
+            _ => Rgb([20, 0, 30]),
+        });
+        let settings = HeightmapSettings {
+            colors: vec![
+                (Color::new(255, 215, 0), GOLD),
+                (Color::new(0, 0, 0), OBSIDIAN),
+            ],
+            ..HeightmapSettings::default()
+        };
+        let grid = heightmap_terrain(
+            &heightmap.into(),
+            Some(&color_map.into()),
+            Vec3::zeros(),
+            1.0,
+            &settings,
+        );
+
+        assert_eq!(grid.get_block(1, 16, 0), Some(&GOLD));
+        assert_eq!(grid.get_block(0, 16, 1), Some(&OBSIDIAN));
+        assert_eq!(grid.get_block(0, 15, 1), Some(&STONE));
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use glm::Vec3;
+use rand::RngCore;
+
+use crate::color::Radiance;
+use crate::light::Light;
+use crate::path_tracer::PathTracer;
+use crate::raytracer::Traceable;
+use crate::render::cast_ray;
+use crate::Model;
+
+/// An algorithm that computes how much light arrives through a ray.
+///
+/// `lights` are all the lights of the frame, the ones of the model plus the top light,
+/// and `time` is how many seconds into their animation the textures are.
+/// Integrators that need random numbers must take them from `rng`.
+pub trait Integrator: Debug + Send + Sync {
+    /// Short name used to pick the integrator, e.g. from the command line.
+    fn name(&self) -> &'static str;
+
+    fn radiance(
+        &self,
+        ray_origin: &Vec3,
+        ray_direction: &Vec3,
+        data: &Model,
+        lights: &[Light],
+        time: f32,
+        rng: &mut dyn RngCore,
+    ) -> Radiance;
+}
+
+/// Direct lighting with Phong highlights, mirror reflections and refractions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Whitted;
+
+impl Integrator for Whitted {
+    fn name(&self) -> &'static str {
+        "whitted"
+    }
+
+    fn radiance(
+        &self,
+        ray_origin: &Vec3,
+        ray_direction: &Vec3,
+        data: &Model,
+        lights: &[Light],
+        time: f32,
+        _rng: &mut dyn RngCore,
+    ) -> Radiance {
+        cast_ray(ray_origin, ray_direction, data, lights, time, 0)
+    }
+}
+
+/// Shows the normal of the first surface hit as a color, useful to debug geometry.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn name(&self) -> &'static str {
+        "normals"
+    }
+
+    fn radiance(
+        &self,
+        ray_origin: &Vec3,
+        ray_direction: &Vec3,
+        data: &Model,
+        _lights: &[Light],
+        _time: f32,
+        _rng: &mut dyn RngCore,
+    ) -> Radiance {
+        match data.scene.ray_intersect(ray_origin, ray_direction) {
+            Some(intersect) => {
+                let color = intersect.normal * 0.5 + Vec3::repeat(0.5);
+                Radiance::new(color.x, color.y, color.z)
+            }
+            None => Radiance::black(),
+        }
+    }
+}
+
+/// Every integrator that comes with the library, with their default settings.
+pub fn builtin_integrators() -> Vec<Arc<dyn Integrator>> {
+    vec![
+        Arc::new(Whitted),
+        Arc::new(PathTracer::default()),
+        Arc::new(Normals),
+    ]
+}
+
+/// Gets one of the built-in integrators by its name, ignoring case.
+pub fn integrator_from_name(name: &str) -> Option<Arc<dyn Integrator>> {
+    let name = match name.to_lowercase().as_str() {
+        "path_tracer" | "pathtracer" => "path".to_string(),
+        name => name.to_string(),
+    };
+
+    builtin_integrators()
+        .into_iter()
+        .find(|integrator| integrator.name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_integrators_are_found_by_name() {
+        for integrator in builtin_integrators() {
+            let found = integrator_from_name(&integrator.name().to_uppercase()).unwrap();
+            assert_eq!(found.name(), integrator.name());
+        }
+
+        assert_eq!(integrator_from_name("path_tracer").unwrap().name(), "path");
+        assert!(integrator_from_name("rasterizer").is_none());
+    }
+}
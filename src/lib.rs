@@ -1,22 +1,43 @@
 use camera::Camera;
 use color::Color;
-use cube::Cube;
 use glm::Vec3;
 use light::{AmbientLightIntensity, Light};
-use sphere::Sphere;
-use texture::GameTextures;
+use render::{DAY_COLOR, NIGHT_COLOR};
+use scene::Scene;
+use texture::TextureRegistry;
 
+pub mod accumulation;
+pub mod anvil;
 pub mod bmp;
+pub mod bvh;
 pub mod camera;
 pub mod color;
 pub mod cube;
 pub mod framebuffer;
+pub mod gltf_scene;
+pub mod heightmap;
+pub mod integrator;
 pub mod light;
 pub mod material;
+pub mod mesh;
+pub mod minecraft;
+pub mod nbt;
+pub mod obj;
+pub mod path_tracer;
 pub mod raytracer;
 pub mod render;
+pub mod resource_pack;
+pub mod sampling;
+pub mod scene;
+pub mod scene_file;
+pub mod schematic;
 pub mod sphere;
+pub mod terrain;
 pub mod texture;
+pub mod tonemap;
+pub mod vox;
+pub mod voxel;
+pub mod world;
 
 extern crate nalgebra_glm as glm;
 
@@ -39,13 +60,38 @@ pub enum TimeOfDay {
     Night,
 }
 
+impl TimeOfDay {
+    pub fn sky_color(&self) -> Color {
+        match self {
+            TimeOfDay::Day => DAY_COLOR,
+            TimeOfDay::Night => NIGHT_COLOR,
+        }
+    }
+
+    /// The light that illuminates the scene from above.
+    pub fn top_light(&self) -> Light {
+        let position = Vec3::new(0.0, 20.0, 0.0);
+        match self {
+            TimeOfDay::Day => Light {
+                position,
+                color: Color::white(),
+                intensity: 1.0,
+            },
+            TimeOfDay::Night => Light {
+                position,
+                color: Color::black(),
+                intensity: 0.5,
+            },
+        }
+    }
+}
+
 pub struct Model {
-    pub spheres: Vec<Sphere>,
-    pub cubes: Vec<Cube>,
+    pub scene: Scene,
     pub lights: Vec<Light>,
     pub ambient_light: AmbientLightIntensity,
     pub camera: Camera,
-    pub textures: GameTextures,
+    pub textures: TextureRegistry,
     pub daytime: TimeOfDay,
     pub sky_color: Color,
     pub top_light: Light,
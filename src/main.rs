@@ -1,25 +1,36 @@
 use minifb::{Key, KeyRepeat, Window, WindowOptions};
 use mouse_rs::Mouse;
-use nalgebra_glm::{Vec2, Vec3};
-use rand::{thread_rng, Rng};
-use rayon::iter::ParallelIterator;
-use raytracer::camera::Camera;
-use raytracer::color::Color;
-use raytracer::cube::Cube;
-use raytracer::light::Light;
-use raytracer::material::{Material, GOLD, MAGMA, NETHERRACK, OBSIDIAN, PORTAL, STONE};
-use raytracer::render::{init_render, render, DAY_COLOR, NIGHT_COLOR};
-use raytracer::sphere::Sphere;
-use raytracer::texture::GameTextures;
+use raytracer::accumulation::AccumulationBuffer;
+use raytracer::integrator::builtin_integrators;
+use raytracer::render::{
+    animated_textures_visible, init_render, render_progressive, RenderSettings,
+};
+use raytracer::scene_file::load_model;
+use raytracer::texture::TextureRegistry;
+use raytracer::world::nether_portal;
 use raytracer::{framebuffer, TimeOfDay};
 use raytracer::{Message, Model};
 use std::collections::VecDeque;
 use std::env;
 use std::f32::consts::PI;
+use std::process::exit;
 use std::time::{Duration, Instant};
 
 const PLAYER_SPEED: f32 = 0.1;
 const PLAYER_ROTATION_SPEED: f32 = PI / 20.0;
+const EXPOSURE_STEP: f32 = 0.5;
+/// Samples per pixel added every frame once the camera stops moving, while moving only one is used.
+const STILL_SAMPLES_PER_PIXEL: u32 = 4;
+/// Once every pixel has this many samples the image is considered converged and stops being rendered.
+const MAX_ACCUMULATED_SAMPLES: u32 = 256;
+
+const USAGE: &str = "Usage: raytracer [asset_dir] [options]
+
+The asset directory has textures that replace the built-in ones, or is a Minecraft resource pack.
+
+Options:
+    --scene <path>  Scene file (.json, .gltf or .glb) to show (default: the built-in nether portal)
+    --seed <n>      Seed of the built-in scene and of the sampling (default: 0)";
 
 fn main() {
     let window_width = 1080;
@@ -46,8 +57,19 @@ fn main() {
     let target_framerate = 60;
     let frame_delay = Duration::from_millis(1000 / target_framerate);
 
-    let mut data = init(framebuffer_width, framebuffer_height);
-    init_render(&mut framebuffer, &data);
+    let (mut data, seed) = init();
+    let mut settings = RenderSettings {
+        samples_per_pixel: STILL_SAMPLES_PER_PIXEL,
+        seed,
+        ..RenderSettings::default()
+    };
+    init_render(&mut framebuffer, &data, &settings);
+    let mut accumulation = AccumulationBuffer::new(framebuffer_width, framebuffer_height);
+    let animation_start = Instant::now();
+    let mut animating = animated_textures_visible(&data, framebuffer_width, framebuffer_height);
+    let mut animation_step = data.textures.animation_step();
+    let integrators = builtin_integrators();
+    let mut integrator_index = 0;
 
     let mut splash_timer = 0;
     let splash_delay = 300;
@@ -88,6 +110,38 @@ fn main() {
                     })
                 }
 
+                Key::T => {
+                    should_update = true;
+                    settings.display.operator = settings.display.operator.next();
+                    None
+                }
+                Key::E => {
+                    should_update = true;
+                    settings.display.auto_exposure = !settings.display.auto_exposure;
+                    None
+                }
+                Key::P => {
+                    should_update = true;
+                    integrator_index = (integrator_index + 1) % integrators.len();
+                    settings.integrator = integrators[integrator_index].clone();
+                    None
+                }
+                Key::F => {
+                    should_update = true;
+                    settings.filter = settings.filter.next();
+                    None
+                }
+                Key::Equal => {
+                    should_update = true;
+                    settings.display.exposure += EXPOSURE_STEP;
+                    None
+                }
+                Key::Minus => {
+                    should_update = true;
+                    settings.display.exposure -= EXPOSURE_STEP;
+                    None
+                }
+
                 // Key::Space => match (mode_cooldown_timer, &data.status) {
                 //     (0, GameStatus::MainMenu) => {
                 //         mode_cooldown_timer = mode_cooldown;
@@ -110,8 +164,32 @@ fn main() {
             data = update(data, msg);
         }
 
-        if data.camera.has_changed() || should_update {
-            render(&mut framebuffer, &data);
+        let moved = data.camera.has_changed() || should_update;
+        if moved {
+            accumulation.reset();
+            animating = animated_textures_visible(&data, framebuffer_width, framebuffer_height);
+            animation_step = data.textures.animation_step();
+        }
+        // Time only advances once the textures would look different, so samples keep
+        // accumulating in between
+        if let (true, Some(step)) = (animating, animation_step) {
+            let time = (animation_start.elapsed().as_secs_f32() / step).floor() * step;
+            if time != settings.time {
+                settings.time = time;
+                accumulation.reset();
+            }
+        }
+        if accumulation.samples_per_pixel() < MAX_ACCUMULATED_SAMPLES {
+            // Keep the first frame after a change cheap so moving around stays responsive
+            let pass_settings = if accumulation.passes() == 0 && moved {
+                RenderSettings {
+                    samples_per_pixel: 1,
+                    ..settings.clone()
+                }
+            } else {
+                settings.clone()
+            };
+            render_progressive(&mut framebuffer, &mut accumulation, &data, &pass_settings);
         }
         data.camera.reset_change();
 
@@ -128,135 +206,94 @@ fn main() {
         let avg_millis: f32 = last_recorded_frames.iter().map(|&u| u as f32).sum::<f32>()
             / last_recorded_frames_max_count as f32;
         let avg_frames = 1000.0 / avg_millis;
-        window.set_title(format!("{} - {:.2} fps", title_prefix, avg_frames).as_ref());
+        let display = &settings.display;
+        window.set_title(
+            format!(
+                "{} - {:.2} fps - {} {} spp {:?} - {:?} {:+.1} EV{}",
+                title_prefix,
+                avg_frames,
+                settings.integrator.name(),
+                accumulation.samples_per_pixel(),
+                settings.filter,
+                display.operator,
+                display.exposure,
+                if display.auto_exposure { " (auto)" } else { "" }
+            )
+            .as_ref(),
+        );
         std::thread::sleep(frame_delay);
     }
 }
 
-/// Init the default state
-fn init(framebuffer_width: usize, framebuffer_height: usize) -> Model {
+struct Options {
+    asset_dir: Option<String>,
+    scene: Option<String>,
+    seed: u64,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut args = args.peekable();
+    let mut options = Options {
+        asset_dir: args.next_if(|arg| !arg.starts_with("--")),
+        scene: None,
+        seed: 0,
+    };
+
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for `{flag}`"))?;
+
+        match flag.as_str() {
+            "--scene" => options.scene = Some(value),
+            "--seed" => {
+                options.seed = value
+                    .parse()
+                    .map_err(|e| format!("Invalid seed `{value}`: {e}"))?
+            }
+            _ => return Err(format!("Unknown option `{flag}`")),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Init the default state and the seed of the random numbers from the command line arguments.
+fn init() -> (Model, u64) {
     let mut args = env::args();
     args.next();
 
-    let asset_dir = args.next().expect("No asset directory received!");
-    println!("Reading assets from: {asset_dir}");
-
-    let mut object_id = 0;
-    let p_width_height = 8;
-    let cube_size = 1.5;
-    let gap = 0.0;
-    // let mut cubes = vec![];
-
-    let mut cubes = generate_platform(
-        object_id,
-        Vec3::new(0.0, -cube_size * 1.0, 0.0),
-        p_width_height,
-        gap,
-        cube_size,
-    );
-    object_id += (cubes.len() + 1) as u32;
-
-    let delta = Vec2::new(-1.0, 0.0) * cube_size;
-    let mut obsidian_frame = generate_rectangle(
-        object_id,
-        Vec2::new(-1.0, 0.0) * cube_size + delta,
-        Vec2::new(2.0, 4.0) * cube_size + delta,
-        -3.0 * cube_size,
-        cube_size,
-        OBSIDIAN,
-    );
-    object_id += (cubes.len() + 1) as u32;
-    cubes.append(&mut obsidian_frame);
-
-    let (mut portal_cubes, mut portal_lights, spheres) = generate_portal_wall(
-        object_id,
-        Vec2::new(-1.0, 0.0) * cube_size,
-        Vec2::new(1.0, 3.0) * cube_size,
-        -3.0 * cube_size,
-        cube_size,
-        PORTAL,
-    );
-    object_id += (cubes.len() + 1) as u32;
-    object_id += (spheres.len() + 1) as u32;
-    cubes.append(&mut portal_cubes);
-
-    cubes.push(Cube::new(
-        object_id,
-        Vec3::new(0.0, 0.0, -0.0) * cube_size,
-        cube_size,
-        GOLD,
-        Vec3::new(0.0, 1.0, 0.0).normalize(),
-    ));
-
-    cubes.push(Cube::new(
-        object_id,
-        Vec3::new(0.0, 0.0, -1.0) * cube_size,
-        cube_size,
-        MAGMA,
-        Vec3::new(0.0, 1.0, 0.0).normalize(),
-    ));
-    cubes.push(Cube::new(
-        object_id,
-        Vec3::new(-1.0, 0.0, -1.0) * cube_size,
-        cube_size,
-        MAGMA,
-        Vec3::new(0.0, 1.0, 0.0).normalize(),
-    ));
-    cubes.push(Cube::new(
-        object_id,
-        Vec3::new(-1.0, 0.0, -1.0) * cube_size,
-        cube_size,
-        MAGMA,
-        Vec3::new(0.0, 1.0, 0.0).normalize(),
-    ));
-    cubes.push(Cube::new(
-        object_id,
-        Vec3::new(1.0, 0.0, 1.0) * cube_size,
-        cube_size,
-        STONE,
-        Vec3::new(0.0, 1.0, 0.0).normalize(),
-    ));
-
-    // let mut water_cubes = generate_platform(
-    //     object_id,
-    //     Vec3::new(0.0, cube_size * 2.0, 0.0),
-    //     2,
-    //     gap,
-    //     cube_size,
-    //     WATER,
-    // );
-    // object_id += (cubes.len() + 1) as u32;
-    // cubes.append(&mut water_cubes);
-
-    println!("Cubes created: {cubes:#?}");
-
-    let top_light = Light {
-        position: Vec3::new(0.0, 20.0, 0.0),
-        color: Color::white(),
-        intensity: 1.0,
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}\n\n{USAGE}");
+            exit(1);
+        }
     };
 
-    let ambient_light = 0.15;
-
-    let camera = Camera::new(
-        Vec3::new(0.0, 0.0, 10.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-    );
-
-    let textures = GameTextures::new(&asset_dir);
-
-    Model {
-        spheres,
-        cubes,
-        camera,
-        top_light,
-        lights: portal_lights,
-        ambient_light,
-        textures,
-        daytime: raytracer::TimeOfDay::Day,
-        sky_color: DAY_COLOR,
-    }
+    let textures = match &options.asset_dir {
+        Some(asset_dir) => {
+            println!("Reading assets from: {asset_dir}");
+            TextureRegistry::new(asset_dir)
+        }
+        None => TextureRegistry::builtin(),
+    };
+
+    let data = match &options.scene {
+        Some(scene_file) => {
+            println!("Loading scene from: {scene_file}");
+            match load_model(scene_file, textures) {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("{error}");
+                    exit(1);
+                }
+            }
+        }
+        None => nether_portal(textures, options.seed),
+    };
+
+    (data, options.seed)
 }
 
 fn update(data: Model, msg: Message) -> Model {
@@ -282,12 +319,8 @@ fn update(data: Model, msg: Message) -> Model {
         }
         Message::TimeToDay => {
             let daytime = TimeOfDay::Day;
-            let sky_color = DAY_COLOR;
-            let top_light = Light {
-                position: Vec3::new(0.0, 20.0, 0.0),
-                color: Color::white(),
-                intensity: 1.0,
-            };
+            let sky_color = daytime.sky_color();
+            let top_light = daytime.top_light();
 
             Model {
                 daytime,
@@ -298,12 +331,8 @@ fn update(data: Model, msg: Message) -> Model {
         }
         Message::TimeToNight => {
             let daytime = TimeOfDay::Night;
-            let sky_color = NIGHT_COLOR;
-            let top_light = Light {
-                position: Vec3::new(0.0, 20.0, 0.0),
-                color: Color::black(),
-                intensity: 0.5,
-            };
+            let sky_color = daytime.sky_color();
+            let top_light = daytime.top_light();
 
             Model {
                 daytime,
@@ -314,149 +343,3 @@ fn update(data: Model, msg: Message) -> Model {
         }
     }
 }
-
-fn generate_platform(
-    id_count: u32,
-    center: Vec3,
-    size: u16,
-    gap: f32,
-    cube_size: f32,
-) -> Vec<Cube> {
-    let mut object_id = id_count;
-    let half_size: i32 = (size / 2) as i32;
-    (-half_size..half_size)
-        .map(|z| z as f32 * (cube_size + gap))
-        .flat_map(|z| {
-            (-half_size..half_size)
-                .map(|x| x as f32 * (cube_size + gap))
-                .map(move |x| {
-                    let id = object_id;
-                    object_id += 1;
-
-                    let mut rng = thread_rng();
-                    let rn: f32 = rng.gen();
-                    let material = if rn < 0.6 {
-                        NETHERRACK
-                    } else if rn < 0.8 {
-                        STONE
-                    } else if rn < 0.95 {
-                        MAGMA
-                    } else {
-                        GOLD
-                    };
-                    Cube::new(
-                        id,
-                        Vec3::new(x, 0.0, z) + center,
-                        cube_size,
-                        material.clone(),
-                        Vec3::new(0.0, 0.0, 1.0).normalize(),
-                    )
-                })
-        })
-        .collect()
-}
-
-fn generate_rectangle(
-    id_count: u32,
-    start: Vec2,
-    end: Vec2,
-    z: f32,
-    cube_size: f32,
-    material: Material,
-) -> Vec<Cube> {
-    let mut object_id = id_count;
-    let endx = (end.x - start.x) as i32;
-    let endy = (end.y - start.y) as i32;
-
-    let top_and_bottom = vec![start.y, end.y].into_iter().flat_map(|ypos| {
-        let material = material.clone();
-        (0..endx)
-            .map(|xpos| xpos as f32 * cube_size)
-            .map(move |xpos| {
-                let id = object_id;
-                object_id += 1;
-
-                Cube::new(
-                    id,
-                    Vec3::new(xpos + start.x, ypos, z),
-                    cube_size,
-                    material.clone(),
-                    Vec3::new(0.0, 0.0, 1.0).normalize(),
-                )
-            })
-    });
-    // let top_and_bottom = vec![].into_iter();
-
-    let sides = vec![start.x, end.x].into_iter().flat_map(|xpos| {
-        let material = material.clone();
-        (1..(endy - 1))
-            .map(|ypos| ypos as f32 * cube_size)
-            .map(move |ypos| {
-                let id = object_id;
-                object_id += 1;
-
-                Cube::new(
-                    id,
-                    Vec3::new(xpos, ypos + start.y, z),
-                    cube_size,
-                    material.clone(),
-                    Vec3::new(0.0, 0.0, 1.0).normalize(),
-                )
-            })
-    });
-    // let sides = vec![].into_iter();
-
-    top_and_bottom.chain(sides).collect()
-}
-
-fn generate_portal_wall(
-    id_count: u32,
-    start: Vec2,
-    end: Vec2,
-    z: f32,
-    cube_size: f32,
-    material: Material,
-) -> (Vec<Cube>, Vec<Light>, Vec<Sphere>) {
-    let mut object_id = id_count;
-    let endx = (end.x - start.x) as i32;
-    let endy = (end.y - start.y) as i32;
-
-    let cubes = (0..endy)
-        .map(|ypos| ypos as f32 * cube_size)
-        .flat_map(|ypos| {
-            let material = material.clone();
-            (0..endx)
-                .map(|xpos| xpos as f32 * cube_size)
-                .map(move |xpos| {
-                    let id = object_id;
-                    object_id += 1;
-
-                    Cube::new(
-                        id,
-                        Vec3::new(xpos + start.x, ypos, z),
-                        cube_size,
-                        material.clone(),
-                        Vec3::new(0.0, 0.0, 1.0).normalize(),
-                    )
-                })
-        })
-        .collect();
-
-    let light_position = Vec3::new((end.x + start.x) / 2.0, (end.y + start.y) / 2.0, z + 1.0);
-    let light_sources = vec![Light {
-        position: light_position,
-        color: 0x361B6F.into(),
-        intensity: 0.25,
-    }];
-
-    // let spheres = vec![Sphere {
-    //     id: id_count,
-    //     center: light_position,
-    //     radius: 0.2,
-    //     material: RUBBER,
-    // }];
-
-    let spheres = vec![];
-
-    (cubes, light_sources, spheres)
-}
@@ -1,9 +1,14 @@
-use crate::{color::Color, texture::Textures};
+use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+use crate::{
+    color::Color,
+    texture::{Texture, TextureId, TextureRegistry},
+};
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Material {
     pub diffuse: Color,
-    pub texture: Option<Textures>,
+    pub texture: Option<TextureId>,
     pub specular: f32,
     /// La cantidad de luz que un material absorbe, en porcentaje (0,1).
     /// y también
@@ -15,6 +20,13 @@ pub struct Material {
     pub transparency: f32,
     /// El índice refractivo del material.
     pub refractive_index: f32,
+    /// La cantidad de luz que emite el material, multiplica su color (0 no emite luz).
+    pub emission: f32,
+    /// El color de la luz que emite el material, si no tiene se usa el color de la superficie.
+    pub emission_color: Option<Color>,
+    /// Una imagen que cubre la superficie según sus coordenadas de textura, multiplicada por `diffuse`.
+    /// A diferencia de `texture` no está dividida en las caras de un bloque.
+    pub texture_map: Option<Arc<Texture>>,
 }
 
 impl Material {
@@ -26,9 +38,49 @@ impl Material {
             reflectivity: 0.0,
             transparency: 0.0,
             refractive_index: 0.0,
+            emission: 0.0,
+            emission_color: None,
+            texture_map: None,
             texture: None,
         }
     }
+
+    /// Gets one of the predefined materials by its name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let material = match name.to_uppercase().as_str() {
+            "DIRT" => DIRT,
+            "STONE" => STONE,
+            "MOSS" => MOSS,
+            "WATER" => WATER,
+            "OBSIDIAN" => OBSIDIAN,
+            "PORTAL" => PORTAL,
+            "NETHERRACK" => NETHERRACK,
+            "MAGMA" => MAGMA,
+            "GOLD" => GOLD,
+            "RUBBER" => RUBBER,
+            _ => return None,
+        };
+
+        Some(material)
+    }
+
+    /// Whether the material changes with time because its texture is animated.
+    pub fn is_animated(&self, textures: &TextureRegistry) -> bool {
+        self.texture
+            .is_some_and(|texture| textures.is_animated(texture))
+            || self
+                .texture_map
+                .as_ref()
+                .is_some_and(|texture_map| texture_map.is_animated())
+    }
+
+    /// A plain block covered by a texture, like the predefined blocks.
+    pub fn from_texture(texture: TextureId) -> Self {
+        Material {
+            texture: Some(texture),
+            ..STONE
+        }
+    }
 }
 
 pub const DIRT: Material = Material {
@@ -38,7 +90,10 @@ pub const DIRT: Material = Material {
     reflectivity: 0.0,
     transparency: 0.0,
     refractive_index: 1.42,
-    texture: Some(Textures::DIRT),
+    emission: 0.0,
+    emission_color: None,
+    texture_map: None,
+    texture: Some(TextureId::DIRT),
 };
 
 pub const STONE: Material = Material {
@@ -48,7 +103,23 @@ pub const STONE: Material = Material {
     reflectivity: 0.0,
     transparency: 0.0,
     refractive_index: 1.42,
-    texture: Some(Textures::STONE),
+    emission: 0.0,
+    emission_color: None,
+    texture_map: None,
+    texture: Some(TextureId::STONE),
+};
+
+pub const MOSS: Material = Material {
+    diffuse: Color::pink(),
+    specular: 1.0,
+    albedo: (0.95, 0.05),
+    reflectivity: 0.0,
+    transparency: 0.0,
+    refractive_index: 1.42,
+    emission: 0.0,
+    emission_color: None,
+    texture_map: None,
+    texture: Some(TextureId::MOSS),
 };
 
 pub const WATER: Material = Material {
@@ -58,7 +129,10 @@ pub const WATER: Material = Material {
     reflectivity: 0.1,
     transparency: 0.9,
     refractive_index: 1.25,
-    texture: Some(Textures::WATER),
+    emission: 0.0,
+    emission_color: None,
+    texture_map: None,
+    texture: Some(TextureId::WATER),
 };
 
 pub const OBSIDIAN: Material = Material {
@@ -68,7 +142,10 @@ pub const OBSIDIAN: Material = Material {
     reflectivity: 0.0,
     transparency: 0.0,
     refractive_index: 1.25,
-    texture: Some(Textures::OBSIDIAN),
+    emission: 0.0,
+    emission_color: None,
+    texture_map: None,
+    texture: Some(TextureId::OBSIDIAN),
 };
 
 pub const PORTAL: Material = Material {
@@ -78,7 +155,10 @@ pub const PORTAL: Material = Material {
     reflectivity: 0.0,
     transparency: 0.4,
     refractive_index: 1.2,
-    texture: Some(Textures::PORTAL),
+    emission: 0.0,
+    emission_color: None,
+    texture_map: None,
+    texture: Some(TextureId::PORTAL),
 };
 
 pub const NETHERRACK: Material = Material {
@@ -88,7 +168,10 @@ pub const NETHERRACK: Material = Material {
     reflectivity: 0.0,
     transparency: 0.0,
     refractive_index: 1.2,
-    texture: Some(Textures::NETHERRACK),
+    emission: 0.0,
+    emission_color: None,
+    texture_map: None,
+    texture: Some(TextureId::NETHERRACK),
 };
 
 pub const MAGMA: Material = Material {
@@ -98,7 +181,10 @@ pub const MAGMA: Material = Material {
     reflectivity: 0.0,
     transparency: 0.0,
     refractive_index: 1.2,
-    texture: Some(Textures::MAGMA),
+    emission: 1.5,
+    emission_color: None,
+    texture_map: None,
+    texture: Some(TextureId::MAGMA),
 };
 
 pub const GOLD: Material = Material {
@@ -108,7 +194,10 @@ pub const GOLD: Material = Material {
     reflectivity: 0.1,
     transparency: 0.0,
     refractive_index: 1.2,
-    texture: Some(Textures::GOLD),
+    emission: 0.0,
+    emission_color: None,
+    texture_map: None,
+    texture: Some(TextureId::GOLD),
 };
 
 pub const RUBBER: Material = Material {
@@ -118,5 +207,8 @@ pub const RUBBER: Material = Material {
     reflectivity: 0.0,
     transparency: 0.0,
     refractive_index: 1.51,
+    emission: 0.0,
+    emission_color: None,
+    texture_map: None,
     texture: None,
 };
@@ -0,0 +1,204 @@
+use glm::{Vec2, Vec3};
+
+use crate::{
+    cube::BoxBounds,
+    material::Material,
+    raytracer::{Intersect, Traceable},
+    scene::Scene,
+    texture::CubeFace,
+};
+
+/// Rays closer than this to being parallel to a triangle don't hit it.
+const PARALLEL_EPSILON: f32 = 1e-8;
+
+/// A single triangle, optionally with normals and texture coordinates for each vertex.
+///
+/// Block textures are sampled as if the triangle was the front face of a block.
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    /// The vertices in counter clockwise order when looking at the front of the triangle.
+    pub vertices: [Vec3; 3],
+    /// Normals interpolated across the triangle for smooth shading,
+    /// without them the triangle is flat shaded.
+    pub normals: Option<[Vec3; 3]>,
+    /// Texture coordinates of each vertex, (0, 0) being the bottom left corner of the texture.
+    pub uvs: Option<[Vec2; 3]>,
+    pub material: Material,
+}
+
+impl Triangle {
+    /// Creates a new flat shaded triangle without texture coordinates.
+    pub fn new(vertices: [Vec3; 3], material: Material) -> Self {
+        Triangle {
+            vertices,
+            normals: None,
+            uvs: None,
+            material,
+        }
+    }
+
+    /// The normal of the plane of the triangle.
+    pub fn face_normal(&self) -> Vec3 {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(&(c - a)).normalize()
+    }
+}
+
+impl Traceable for Triangle {
+    fn bounding_box(&self) -> BoxBounds {
+        self.vertices
+            .iter()
+            .fold(BoxBounds::empty(), |bounds, vertex| bounds.grow(vertex))
+    }
+
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Option<Intersect> {
+        // Möller–Trumbore:
+        // https://www.scratchapixel.com/lessons/3d-basic-rendering/ray-tracing-rendering-a-triangle/moller-trumbore-ray-triangle-intersection.html
+        let [a, b, c] = self.vertices;
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = ray_direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < PARALLEL_EPSILON {
+            return None;
+        }
+        let inv_determinant = 1.0 / determinant;
+
+        let t_vec = ray_origin - a;
+        let u = t_vec.dot(&p) * inv_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = t_vec.cross(&edge1);
+        let v = ray_direction.dot(&q) * inv_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(&q) * inv_determinant;
+        if distance < 0.0 {
+            return None;
+        }
+
+        // Barycentric weights of each vertex
+        let w = 1.0 - u - v;
+        let normal = match &self.normals {
+            Some([na, nb, nc]) => (na * w + nb * u + nc * v).normalize(),
+            None => edge1.cross(&edge2).normalize(),
+        };
+        let texture_cords = match &self.uvs {
+            Some([ta, tb, tc]) => {
+                let uv = ta * w + tb * u + tc * v;
+                // Textures are read from the top, UVs start at the bottom.
+                Vec2::new(uv.x.rem_euclid(1.0), 1.0 - uv.y.rem_euclid(1.0))
+            }
+            None => Vec2::new(u, v),
+        };
+
+        Some(Intersect {
+            distance,
+            point: ray_origin + ray_direction * distance,
+            normal,
+            material: self.material.clone(),
+            texture_cords,
+            face: CubeFace::FORWARDS,
+        })
+    }
+}
+
+/// A group of triangles with their own bounding volume hierarchy,
+/// so the whole model can be added to a `Scene` as a single object.
+#[derive(Debug, Default)]
+pub struct Mesh {
+    triangles: Scene,
+}
+
+impl Mesh {
+    pub fn new(triangles: Vec<Triangle>) -> Self {
+        let mut mesh = Mesh::default();
+        mesh.triangles.extend(triangles);
+        mesh
+    }
+
+    /// How many triangles the mesh has.
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+}
+
+impl Traceable for Mesh {
+    fn bounding_box(&self) -> BoxBounds {
+        self.triangles.bounding_box()
+    }
+
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Option<Intersect> {
+        self.triangles.ray_intersect(ray_origin, ray_direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::RUBBER;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            [
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 2.0, 0.0),
+            ],
+            RUBBER,
+        )
+    }
+
+    #[test]
+    fn test_hits_inside_and_misses_outside() {
+        let triangle = triangle();
+        let direction = Vec3::new(0.0, 0.0, -1.0);
+
+        let intersect = triangle
+            .ray_intersect(&Vec3::new(0.5, 0.5, 3.0), &direction)
+            .unwrap();
+        assert!((intersect.distance - 3.0).abs() < 1e-5);
+        assert!((intersect.normal - Vec3::new(0.0, 0.0, 1.0)).magnitude() < 1e-5);
+
+        assert!(triangle
+            .ray_intersect(&Vec3::new(1.5, 1.5, 3.0), &direction)
+            .is_none());
+        assert!(triangle
+            .ray_intersect(&Vec3::new(0.5, 0.5, -3.0), &direction)
+            .is_none());
+    }
+
+    #[test]
+    fn test_interpolates_normals_and_uvs() {
+        let triangle = Triangle {
+            normals: Some([
+                Vec3::new(0.0, 0.0, 1.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            ]),
+            uvs: Some([
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(0.0, 1.0),
+            ]),
+            ..triangle()
+        };
+
+        let intersect = triangle
+            .ray_intersect(&Vec3::new(1.0, 0.5, 3.0), &Vec3::new(0.0, 0.0, -1.0))
+            .unwrap();
+
+        let expected_normal = Vec3::new(0.5, 0.0, 0.5).normalize();
+        assert!((intersect.normal - expected_normal).magnitude() < 1e-5);
+        assert!((intersect.texture_cords - Vec2::new(0.5, 0.75)).magnitude() < 1e-5);
+    }
+}
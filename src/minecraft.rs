@@ -0,0 +1,179 @@
+//! Maps Minecraft blocks onto the materials of the renderer.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    material::{Material, DIRT, GOLD, MAGMA, MOSS, NETHERRACK, OBSIDIAN, PORTAL, STONE, WATER},
+    texture::{TextureId, TextureRegistry},
+};
+
+/// The name of a block without its namespace and state,
+/// e.g. `oak_stairs` for `minecraft:oak_stairs[facing=north]`.
+pub fn block_name(block_state: &str) -> &str {
+    let name = block_state
+        .split_once('[')
+        .map_or(block_state, |(name, _)| name);
+    name.strip_prefix("minecraft:").unwrap_or(name)
+}
+
+/// Blocks that leave their cell empty.
+pub fn is_air(name: &str) -> bool {
+    matches!(
+        name,
+        "air" | "cave_air" | "void_air" | "structure_void" | "barrier" | "light"
+    )
+}
+
+/// The material of the blocks that have a look-alike among the predefined materials.
+pub fn builtin_block_material(name: &str) -> Option<Material> {
+    let material = match name {
+        "obsidian" | "crying_obsidian" => OBSIDIAN,
+        "netherrack" | "nether_bricks" | "nether_wart_block" => NETHERRACK,
+        "magma_block" | "lava" => MAGMA,
+        "nether_portal" | "end_portal" => PORTAL,
+        "gold_block" | "raw_gold_block" => GOLD,
+        "water" | "bubble_column" => WATER,
+        "moss_block" | "moss_carpet" => MOSS,
+        "dirt" | "coarse_dirt" | "rooted_dirt" | "grass_block" | "podzol" | "mycelium"
+        | "farmland" | "dirt_path" | "mud" => DIRT,
+        "stone"
+        | "cobblestone"
+        | "mossy_cobblestone"
+        | "stone_bricks"
+        | "mossy_stone_bricks"
+        | "cracked_stone_bricks"
+        | "smooth_stone"
+        | "andesite"
+        | "diorite"
+        | "granite"
+        | "deepslate"
+        | "cobbled_deepslate"
+        | "tuff"
+        | "bedrock"
+        | "gravel" => STONE,
+        _ => return None,
+    };
+
+    Some(material)
+}
+
+/// Decides the material of every block of an imported build.
+///
+/// Blocks are looked for in the overrides first, then among the textures named like them,
+/// then among the built-in ones (see `builtin_block_material`) and get the `fallback`
+/// material when they aren't found.
+#[derive(Debug, Clone)]
+pub struct BlockMaterials {
+    /// Materials by block name (without namespace), `None` leaves the block out.
+    overrides: HashMap<String, Option<Material>>,
+    /// Textures by block name, see `use_textures`.
+    textures: HashMap<String, TextureId>,
+    /// Material of the blocks that aren't known, `None` leaves them out.
+    pub fallback: Option<Material>,
+}
+
+impl Default for BlockMaterials {
+    fn default() -> Self {
+        BlockMaterials::new(Some(STONE))
+    }
+}
+
+impl BlockMaterials {
+    pub fn new(fallback: Option<Material>) -> Self {
+        BlockMaterials {
+            overrides: HashMap::new(),
+            textures: HashMap::new(),
+            fallback,
+        }
+    }
+
+    /// Uses `material` for the blocks called `block`, with or without namespace.
+    pub fn set(&mut self, block: &str, material: Option<Material>) {
+        self.overrides
+            .insert(block_name(block).to_string(), material);
+    }
+
+    /// Blocks named like a texture of `textures`, e.g. `oak_planks`, get a block covered by it.
+    pub fn use_textures(&mut self, textures: &TextureRegistry) {
+        self.textures
+            .extend(textures.names().map(|(name, id)| (name.to_string(), id)));
+    }
+
+    /// Whether the block is known, either as an override, air, a texture or one of the built-in blocks.
+    pub fn is_known(&self, block_state: &str) -> bool {
+        let name = block_name(block_state);
+        self.overrides.contains_key(name)
+            || is_air(name)
+            || self.textures.contains_key(name)
+            || builtin_block_material(name).is_some()
+    }
+
+    /// The material of a block, `None` if its cell should be left empty.
+    pub fn material(&self, block_state: &str) -> Option<Material> {
+        let name = block_name(block_state);
+        if let Some(material) = self.overrides.get(name) {
+            return material.clone();
+        }
+        if is_air(name) {
+            return None;
+        }
+        if let Some(&texture) = self.textures.get(name) {
+            return Some(Material::from_texture(texture));
+        }
+
+        builtin_block_material(name).or_else(|| self.fallback.clone())
+    }
+
+    /// The materials of every block of a palette, warning once about the unknown ones.
+    pub fn palette_materials<'a>(
+        &self,
+        palette: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<Option<Material>> {
+        let mut unknown = BTreeSet::new();
+        let materials = palette
+            .into_iter()
+            .map(|block_state| {
+                if !self.is_known(block_state) {
+                    unknown.insert(block_name(block_state).to_string());
+                }
+                self.material(block_state)
+            })
+            .collect();
+
+        if !unknown.is_empty() {
+            let action = match self.fallback {
+                Some(_) => "using the fallback material",
+                None => "leaving them out",
+            };
+            let names: Vec<String> = unknown.into_iter().collect();
+            eprintln!("Warning: unknown blocks {}, {action}", names.join(", "));
+        }
+
+        materials
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::RUBBER;
+
+    #[test]
+    fn test_block_names_map_to_materials() {
+        let mut materials = BlockMaterials::new(Some(RUBBER));
+        materials.set("minecraft:glowstone", Some(MAGMA));
+
+        assert_eq!(
+            block_name("minecraft:oak_stairs[facing=north]"),
+            "oak_stairs"
+        );
+        assert_eq!(materials.material("minecraft:obsidian"), Some(OBSIDIAN));
+        assert_eq!(materials.material("glowstone"), Some(MAGMA));
+        assert_eq!(materials.material("minecraft:cave_air"), None);
+        assert_eq!(materials.material("minecraft:oak_planks"), Some(RUBBER));
+        assert!(!materials.is_known("minecraft:oak_planks"));
+
+        materials.fallback = None;
+        assert_eq!(materials.material("minecraft:oak_planks"), None);
+    }
+}
@@ -0,0 +1,264 @@
+//! Reads Minecraft's Named Binary Tag format, used by structures, schematics and region files.
+//!
+//! Only reading is supported. Files are either gzip compressed (structures and schematics)
+//! or zlib compressed (chunks inside region files), see `read_gzip_nbt` and `read_zlib_nbt`.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{self, Read},
+};
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+
+/// Compounds and lists nested deeper than this are rejected instead of overflowing the stack.
+const MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NbtTag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<NbtTag>),
+    Compound(HashMap<String, NbtTag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl NbtTag {
+    /// Gets a field of a compound.
+    pub fn get(&self, key: &str) -> Option<&NbtTag> {
+        match self {
+            NbtTag::Compound(fields) => fields.get(key),
+            _ => None,
+        }
+    }
+
+    /// The value of any of the integer tags.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            NbtTag::Byte(value) => Some(*value as i64),
+            NbtTag::Short(value) => Some(*value as i64),
+            NbtTag::Int(value) => Some(*value as i64),
+            NbtTag::Long(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            NbtTag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[NbtTag]> {
+        match self {
+            NbtTag::List(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&HashMap<String, NbtTag>> {
+        match self {
+            NbtTag::Compound(fields) => Some(fields),
+            _ => None,
+        }
+    }
+
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match self {
+            NbtTag::ByteArray(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_int_array(&self) -> Option<&[i32]> {
+        match self {
+            NbtTag::IntArray(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            NbtTag::LongArray(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum NbtError {
+    Io(io::Error),
+    UnknownTag(u8),
+    NegativeLength(i32),
+    TooDeep,
+    NotACompound,
+}
+
+impl Display for NbtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NbtError::Io(error) => write!(f, "{error}"),
+            NbtError::UnknownTag(id) => write!(f, "unknown NBT tag {id}"),
+            NbtError::NegativeLength(length) => write!(f, "negative NBT length {length}"),
+            NbtError::TooDeep => write!(f, "NBT nested more than {MAX_DEPTH} levels"),
+            NbtError::NotACompound => write!(f, "the root NBT tag isn't a compound"),
+        }
+    }
+}
+
+impl std::error::Error for NbtError {}
+
+impl From<io::Error> for NbtError {
+    fn from(error: io::Error) -> Self {
+        NbtError::Io(error)
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], NbtError> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_length(reader: &mut impl Read) -> Result<usize, NbtError> {
+    let length = i32::from_be_bytes(read_array(reader)?);
+    usize::try_from(length).map_err(|_| NbtError::NegativeLength(length))
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String, NbtError> {
+    let length = u16::from_be_bytes(read_array(reader)?) as usize;
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes)?;
+    // Java's modified UTF-8 only differs from UTF-8 for characters names don't use.
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Reads `length` values with `read_value`, without trusting `length` to preallocate.
+fn read_values<R: Read, T>(
+    reader: &mut R,
+    read_value: impl Fn(&mut R) -> Result<T, NbtError>,
+) -> Result<Vec<T>, NbtError> {
+    let length = read_length(reader)?;
+    let mut values = Vec::with_capacity(length.min(4096));
+    for _ in 0..length {
+        values.push(read_value(reader)?);
+    }
+    Ok(values)
+}
+
+fn read_payload(reader: &mut impl Read, tag_id: u8, depth: usize) -> Result<NbtTag, NbtError> {
+    if depth > MAX_DEPTH {
+        return Err(NbtError::TooDeep);
+    }
+
+    let tag = match tag_id {
+        1 => NbtTag::Byte(i8::from_be_bytes(read_array(reader)?)),
+        2 => NbtTag::Short(i16::from_be_bytes(read_array(reader)?)),
+        3 => NbtTag::Int(i32::from_be_bytes(read_array(reader)?)),
+        4 => NbtTag::Long(i64::from_be_bytes(read_array(reader)?)),
+        5 => NbtTag::Float(f32::from_be_bytes(read_array(reader)?)),
+        6 => NbtTag::Double(f64::from_be_bytes(read_array(reader)?)),
+        7 => NbtTag::ByteArray(read_values(reader, |r| {
+            Ok(i8::from_be_bytes(read_array(r)?))
+        })?),
+        8 => NbtTag::String(read_string(reader)?),
+        9 => {
+            let element_id = read_array::<1>(reader)?[0];
+            NbtTag::List(read_values(reader, |r| {
+                read_payload(r, element_id, depth + 1)
+            })?)
+        }
+        10 => {
+            let mut fields = HashMap::new();
+            loop {
+                let field_id = read_array::<1>(reader)?[0];
+                if field_id == 0 {
+                    break;
+                }
+                let name = read_string(reader)?;
+                fields.insert(name, read_payload(reader, field_id, depth + 1)?);
+            }
+            NbtTag::Compound(fields)
+        }
+        11 => NbtTag::IntArray(read_values(reader, |r| {
+            Ok(i32::from_be_bytes(read_array(r)?))
+        })?),
+        12 => NbtTag::LongArray(read_values(reader, |r| {
+            Ok(i64::from_be_bytes(read_array(r)?))
+        })?),
+        id => return Err(NbtError::UnknownTag(id)),
+    };
+
+    Ok(tag)
+}
+
+/// Reads uncompressed NBT, returning the name of the root compound and the compound itself.
+pub fn read_nbt(mut reader: impl Read) -> Result<(String, NbtTag), NbtError> {
+    let tag_id = read_array::<1>(&mut reader)?[0];
+    if tag_id != 10 {
+        return Err(NbtError::NotACompound);
+    }
+
+    let name = read_string(&mut reader)?;
+    let root = read_payload(&mut reader, tag_id, 0)?;
+    Ok((name, root))
+}
+
+/// Reads gzip compressed NBT, like the one of structure and schematic files.
+pub fn read_gzip_nbt(reader: impl Read) -> Result<(String, NbtTag), NbtError> {
+    read_nbt(GzDecoder::new(reader))
+}
+
+/// Reads zlib compressed NBT, like the one of the chunks of region files.
+pub fn read_zlib_nbt(reader: impl Read) -> Result<(String, NbtTag), NbtError> {
+    read_nbt(ZlibDecoder::new(reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    #[test]
+    fn test_reads_nested_tags() {
+        let mut bytes = vec![10, 0, 4];
+        bytes.extend(b"root");
+        // A string and a list of two ints
+        bytes.extend([8, 0, 4]);
+        bytes.extend(b"name");
+        bytes.extend([0, 8]);
+        bytes.extend(b"obsidian");
+        bytes.extend([9, 0, 4]);
+        bytes.extend(b"size");
+        bytes.extend([3, 0, 0, 0, 2, 0, 0, 0, 7, 0xFF, 0xFF, 0xFF, 0xFE]);
+        bytes.push(0);
+
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&bytes).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let (name, root) = read_gzip_nbt(compressed.as_slice()).unwrap();
+        assert_eq!(name, "root");
+        assert_eq!(root.get("name").and_then(NbtTag::as_str), Some("obsidian"));
+        assert_eq!(
+            root.get("size").and_then(NbtTag::as_list),
+            Some(&[NbtTag::Int(7), NbtTag::Int(-2)][..])
+        );
+
+        assert!(matches!(
+            read_nbt(&bytes[..bytes.len() - 1]),
+            Err(NbtError::Io(_))
+        ));
+    }
+}
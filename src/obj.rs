@@ -0,0 +1,276 @@
+//! Loads Wavefront OBJ models, along with the MTL files they reference, as a `Mesh`.
+//!
+//! MTL materials are mapped onto `Material` like this:
+//!
+//! * `Kd` is the diffuse color and `Ks` how much light is reflected as highlights (`albedo`).
+//! * `Ns` is the specular exponent and `Ni` the refractive index.
+//! * `d` (or `Tr`) is how opaque (or transparent) the material is.
+//! * `illum` 3 to 7 turn on mirror reflections as strong as `Ks`.
+//! * `Ke` is the color and strength of the emitted light.
+//! * `map_Kd` is matched by its file name with the textures of the registry, e.g. `stone.png`.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use glm::{Vec2, Vec3};
+
+use crate::{
+    color::Color,
+    material::Material,
+    mesh::{Mesh, Triangle},
+    texture::TextureRegistry,
+};
+
+/// Used for the parts of a model without a material or when the MTL file can't be loaded.
+pub const DEFAULT_MESH_MATERIAL: Material = Material {
+    diffuse: Color::new(0xCC, 0xCC, 0xCC),
+    specular: 10.0,
+    albedo: (0.9, 0.1),
+    reflectivity: 0.0,
+    transparency: 0.0,
+    refractive_index: 1.0,
+    emission: 0.0,
+    emission_color: None,
+    texture_map: None,
+    texture: None,
+};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Load(PathBuf, tobj::LoadError),
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjError::Load(path, error) => {
+                write!(f, "Couldn't load model {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Where to place a model loaded from a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshTransform {
+    /// Where the origin of the model ends up.
+    pub position: Vec3,
+    /// Every vertex is multiplied by it before being moved to `position`.
+    pub scale: f32,
+}
+
+impl Default for MeshTransform {
+    fn default() -> Self {
+        MeshTransform {
+            position: Vec3::zeros(),
+            scale: 1.0,
+        }
+    }
+}
+
+fn to_color(rgb: [f32; 3]) -> Color {
+    Color::new(
+        (rgb[0].clamp(0.0, 1.0) * 255.0).round() as u8,
+        (rgb[1].clamp(0.0, 1.0) * 255.0).round() as u8,
+        (rgb[2].clamp(0.0, 1.0) * 255.0).round() as u8,
+    )
+}
+
+fn average(rgb: [f32; 3]) -> f32 {
+    (rgb[0] + rgb[1] + rgb[2]) / 3.0
+}
+
+fn parse_rgb(value: &str) -> Option<[f32; 3]> {
+    let components: Vec<f32> = value
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    match components[..] {
+        [r, g, b] => Some([r, g, b]),
+        [value] => Some([value; 3]),
+        _ => None,
+    }
+}
+
+/// Converts a material of an MTL file into a `Material`.
+pub fn material_from_mtl(mtl: &tobj::Material, textures: &TextureRegistry) -> Material {
+    let mut material = DEFAULT_MESH_MATERIAL;
+
+    if let Some(diffuse) = mtl.diffuse {
+        material.diffuse = to_color(diffuse);
+    }
+    if let Some(specular) = mtl.specular {
+        let highlights = average(specular).clamp(0.0, 1.0);
+        material.albedo = (1.0 - highlights, highlights);
+
+        if let Some(3..=7) = mtl.illumination_model {
+            material.reflectivity = highlights;
+        }
+    }
+    if let Some(shininess) = mtl.shininess {
+        material.specular = shininess;
+    }
+    if let Some(optical_density) = mtl.optical_density {
+        material.refractive_index = optical_density;
+    }
+
+    let transparency = match (mtl.dissolve, mtl.unknown_param.get("Tr")) {
+        (Some(dissolve), _) => 1.0 - dissolve,
+        (None, Some(tr)) => tr.trim().parse().unwrap_or(0.0),
+        (None, None) => 0.0,
+    };
+    material.transparency = transparency.clamp(0.0, 1.0);
+    material.reflectivity = material.reflectivity.min(1.0 - material.transparency);
+
+    if let Some(emissive) = mtl.unknown_param.get("Ke").and_then(|ke| parse_rgb(ke)) {
+        let strength = emissive[0].max(emissive[1]).max(emissive[2]);
+        if strength > 0.0 {
+            material.emission = strength;
+            material.emission_color = Some(to_color(emissive.map(|c| c / strength)));
+        }
+    }
+
+    if let Some(texture_file) = &mtl.diffuse_texture {
+        let name = Path::new(texture_file)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        match textures.id(&name) {
+            Some(texture) => material.texture = Some(texture),
+            None => eprintln!(
+                "Warning: material `{}` uses the unknown texture `{texture_file}`, using its color instead",
+                mtl.name
+            ),
+        }
+    }
+
+    material
+}
+
+fn read_vec3(values: &[f32], index: usize) -> Vec3 {
+    Vec3::new(
+        values[3 * index],
+        values[3 * index + 1],
+        values[3 * index + 2],
+    )
+}
+
+fn read_vec2(values: &[f32], index: usize) -> Vec2 {
+    Vec2::new(values[2 * index], values[2 * index + 1])
+}
+
+/// Reads the OBJ file at `file_path` and every MTL file it references.
+///
+/// A model whose MTL files can't be loaded is still loaded, with `DEFAULT_MESH_MATERIAL`.
+pub fn load_obj(
+    file_path: impl AsRef<Path>,
+    transform: MeshTransform,
+    textures: &TextureRegistry,
+) -> Result<Mesh, ObjError> {
+    let file_path = file_path.as_ref();
+    let (models, mtl_materials) = tobj::load_obj(file_path, &tobj::GPU_LOAD_OPTIONS)
+        .map_err(|e| ObjError::Load(file_path.to_path_buf(), e))?;
+
+    let materials: Vec<Material> = match mtl_materials {
+        Ok(mtl_materials) => mtl_materials
+            .iter()
+            .map(|mtl| material_from_mtl(mtl, textures))
+            .collect(),
+        Err(error) => {
+            eprintln!(
+                "Warning: couldn't load the materials of {}: {error}",
+                file_path.display()
+            );
+            vec![]
+        }
+    };
+
+    let mut triangles = vec![];
+    for model in models {
+        let mesh = &model.mesh;
+        let material = mesh
+            .material_id
+            .and_then(|id| materials.get(id))
+            .cloned()
+            .unwrap_or(DEFAULT_MESH_MATERIAL);
+
+        for face in mesh.indices.chunks_exact(3) {
+            let indices = [face[0] as usize, face[1] as usize, face[2] as usize];
+
+            let vertices = indices
+                .map(|i| read_vec3(&mesh.positions, i) * transform.scale + transform.position);
+            let normals = (!mesh.normals.is_empty())
+                .then(|| indices.map(|i| read_vec3(&mesh.normals, i).normalize()));
+            let uvs = (!mesh.texcoords.is_empty())
+                .then(|| indices.map(|i| read_vec2(&mesh.texcoords, i)));
+
+            triangles.push(Triangle {
+                vertices,
+                normals,
+                uvs,
+                material: material.clone(),
+            });
+        }
+    }
+
+    Ok(Mesh::new(triangles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::Traceable;
+    use crate::texture::TextureId;
+
+    #[test]
+    fn test_maps_mtl_properties() {
+        let mut mtl = tobj::Material {
+            name: "glowing_glass".to_string(),
+            diffuse: Some([1.0, 0.5, 0.0]),
+            specular: Some([0.4, 0.4, 0.4]),
+            shininess: Some(80.0),
+            dissolve: Some(0.25),
+            optical_density: Some(1.5),
+            illumination_model: Some(3),
+            diffuse_texture: Some("textures/Obsidian.png".to_string()),
+            ..tobj::Material::default()
+        };
+        mtl.unknown_param
+            .insert("Ke".to_string(), "0.0 2.0 0.5".to_string());
+
+        let material = material_from_mtl(&mtl, &TextureRegistry::default());
+
+        assert_eq!(material.diffuse, Color::new(255, 128, 0));
+        assert!((material.albedo.1 - 0.4).abs() < 1e-5);
+        assert_eq!(material.specular, 80.0);
+        assert_eq!(material.transparency, 0.75);
+        assert!((material.reflectivity - 0.25).abs() < 1e-5);
+        assert_eq!(material.refractive_index, 1.5);
+        assert_eq!(material.emission, 2.0);
+        assert_eq!(material.emission_color, Some(Color::new(0, 255, 64)));
+        assert_eq!(material.texture, Some(TextureId::OBSIDIAN));
+    }
+
+    #[test]
+    fn test_loads_the_example_model() {
+        let file_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/models/pyramid.obj");
+        let transform = MeshTransform {
+            position: Vec3::new(0.0, 1.0, 0.0),
+            scale: 2.0,
+        };
+
+        let mesh = load_obj(file_path, transform, &TextureRegistry::default()).unwrap();
+
+        assert_eq!(mesh.len(), 6 + 8);
+        let bounds = mesh.bounding_box();
+        assert!((bounds.min - Vec3::new(-2.0, 1.0, -2.0)).magnitude() < 1e-5);
+        assert!((bounds.max.y - 5.2).abs() < 1e-5);
+    }
+}
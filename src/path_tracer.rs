@@ -0,0 +1,251 @@
+//! Monte Carlo path tracing.
+//!
+//! Every camera ray is continued by a random bounce at each surface it hits, so light that
+//! arrives after bouncing around the scene (color bleeding, light from emissive blocks, the
+//! sky lighting the shadows) is accounted for. One path is a noisy estimate, the image
+//! converges as more samples per pixel are averaged.
+//!
+//! Point lights are sampled explicitly at every diffuse bounce (next event estimation) since
+//! a random bounce can never hit them. Just like `cast_ray` they don't get dimmer with the
+//! distance, `intensity` is the light a surface facing them receives.
+//!
+//! Apart from mirror reflections and refractions surfaces are purely diffuse, the Phong
+//! highlights of `cast_ray` aren't drawn.
+
+use glm::Vec3;
+use rand::{Rng, RngCore};
+
+use crate::color::Radiance;
+use crate::integrator::Integrator;
+use crate::light::Light;
+use crate::material::Material;
+use crate::raytracer::Traceable;
+use crate::render::{reflect, refract, surface_color};
+use crate::sampling::cosine_sample_hemisphere;
+use crate::scene::Scene;
+use crate::Model;
+
+/// Distance new rays are moved away from the surface they start at to avoid hitting it again.
+const SURFACE_OFFSET: f32 = 1e-3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathTracerSettings {
+    /// Paths are cut after this many bounces no matter what.
+    pub max_bounces: u32,
+    /// Bounces before Russian roulette starts terminating paths at random.
+    pub russian_roulette_depth: u32,
+    /// Maximum value any channel of the light carried by an indirect bounce can have.
+    /// Keeps rare, very bright paths from showing up as isolated white pixels.
+    pub firefly_clamp: f32,
+}
+
+impl Default for PathTracerSettings {
+    fn default() -> Self {
+        PathTracerSettings {
+            max_bounces: 16,
+            russian_roulette_depth: 3,
+            firefly_clamp: 10.0,
+        }
+    }
+}
+
+/// `Integrator` that traces a random path for every ray, see `trace_path`.
+#[derive(Debug, Clone, Default)]
+pub struct PathTracer {
+    pub settings: PathTracerSettings,
+}
+
+impl PathTracer {
+    pub fn new(settings: PathTracerSettings) -> Self {
+        PathTracer { settings }
+    }
+}
+
+impl Integrator for PathTracer {
+    fn name(&self) -> &'static str {
+        "path"
+    }
+
+    fn radiance(
+        &self,
+        ray_origin: &Vec3,
+        ray_direction: &Vec3,
+        data: &Model,
+        lights: &[Light],
+        time: f32,
+        rng: &mut dyn RngCore,
+    ) -> Radiance {
+        trace_path(
+            ray_origin,
+            ray_direction,
+            data,
+            lights,
+            time,
+            &self.settings,
+            rng,
+        )
+    }
+}
+
+/// How much of the light leaves through the shadow ray towards `light`.
+///
+/// Transparent occluders let part of the light through.
+fn light_visibility(origin: &Vec3, light: &Light, scene: &Scene) -> f32 {
+    let to_light = light.position - origin;
+    let distance_to_light = to_light.magnitude();
+    let light_dir = to_light / distance_to_light;
+
+    match scene.ray_intersect(origin, &light_dir) {
+        Some(occluder) if occluder.distance < distance_to_light => occluder.material.transparency,
+        _ => 1.0,
+    }
+}
+
+/// The light given off by a surface of `material` whose color is `surface`.
+/// Only the path tracer shows it, `cast_ray` ignores the emission of materials.
+fn emitted_light(material: &Material, surface: Radiance) -> Radiance {
+    if material.emission <= 0.0 {
+        return Radiance::black();
+    }
+
+    match material.emission_color {
+        Some(color) => Radiance::from(color) * material.emission,
+        None => surface * material.emission,
+    }
+}
+
+fn clamp_fireflies(radiance: Radiance, bounce: u32, settings: &PathTracerSettings) -> Radiance {
+    let max = radiance.max_component();
+    if bounce == 0 || max <= settings.firefly_clamp {
+        radiance
+    } else {
+        radiance * (settings.firefly_clamp / max)
+    }
+}
+
+/// Estimates the light arriving at `ray_origin` from `ray_direction` by following a single
+/// random path through the scene.
+pub fn trace_path<R: Rng + ?Sized>(
+    ray_origin: &Vec3,
+    ray_direction: &Vec3,
+    data: &Model,
+    lights: &[Light],
+    time: f32,
+    settings: &PathTracerSettings,
+    rng: &mut R,
+) -> Radiance {
+    let Model {
+        scene,
+        textures,
+        sky_color,
+        ..
+    } = data;
+
+    let mut radiance = Radiance::black();
+    // How much of the light found from now on reaches the camera.
+    let mut throughput = Radiance::new(1.0, 1.0, 1.0);
+    let mut origin = *ray_origin;
+    let mut direction = *ray_direction;
+
+    for bounce in 0..=settings.max_bounces {
+        let intersect = match scene.ray_intersect(&origin, &direction) {
+            Some(intersect) => intersect,
+            None => {
+                let sky = throughput * Radiance::from(sky_color);
+                radiance += clamp_fireflies(sky, bounce, settings);
+                break;
+            }
+        };
+
+        let material = &intersect.material;
+        let surface = surface_color(&intersect, textures, time);
+        if material.emission > 0.0 {
+            let emitted = throughput * emitted_light(material, surface);
+            radiance += clamp_fireflies(emitted, bounce, settings);
+        }
+
+        // The normal facing the side the ray comes from
+        let facing_normal = if intersect.normal.dot(&direction) > 0.0 {
+            -intersect.normal
+        } else {
+            intersect.normal
+        };
+
+        // Pick one of the ways the material scatters light with the probability of its weight,
+        // which cancels the weight out of the throughput.
+        let reflectivity = material.reflectivity;
+        let transparency = material.transparency;
+        let choice: f32 = rng.gen();
+        if choice < reflectivity {
+            direction = reflect(&direction, &intersect.normal).normalize();
+            origin = intersect.point + facing_normal * SURFACE_OFFSET;
+        } else if choice < reflectivity + transparency {
+            direction = refract(&direction, &intersect.normal, material.refractive_index);
+            // Continue from the side of the surface the new ray goes towards
+            let side = if direction.dot(&facing_normal) < 0.0 {
+                -facing_normal
+            } else {
+                facing_normal
+            };
+            origin = intersect.point + side * SURFACE_OFFSET;
+        } else {
+            // Only the diffuse lobe is used, both for the lights and for the bounce, so light
+            // that arrives by bouncing is reflected the same way as the one from point lights.
+            let albedo = surface * material.albedo.0;
+            origin = intersect.point + facing_normal * SURFACE_OFFSET;
+
+            for light in lights {
+                let light_dir = (light.position - intersect.point).normalize();
+                let cosine = facing_normal.dot(&light_dir);
+                if cosine <= 0.0 {
+                    continue;
+                }
+
+                let visibility = light_visibility(&origin, light, scene);
+                if visibility <= 0.0 {
+                    continue;
+                }
+
+                let incoming = Radiance::from(light.color) * light.intensity * visibility;
+                let reflected = albedo * cosine * incoming;
+
+                radiance += clamp_fireflies(throughput * reflected, bounce, settings);
+            }
+
+            // With cosine weighted directions the cosine and the pdf cancel out,
+            // leaving only the albedo.
+            direction = cosine_sample_hemisphere(&facing_normal, rng);
+            throughput = throughput * albedo;
+        }
+
+        if bounce >= settings.russian_roulette_depth {
+            let survival = throughput.max_component().min(0.95);
+            if survival <= 0.0 || rng.gen::<f32>() >= survival {
+                break;
+            }
+            throughput = throughput / survival;
+        }
+    }
+
+    radiance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{MAGMA, STONE};
+
+    #[test]
+    fn test_magma_glows_unless_its_emission_is_turned_off() {
+        let surface = Radiance::new(0.5, 0.25, 0.0);
+
+        assert_eq!(emitted_light(&MAGMA, surface), surface * MAGMA.emission);
+        assert_eq!(emitted_light(&STONE, surface), Radiance::black());
+
+        let cold_magma = Material {
+            emission: 0.0,
+            ..MAGMA
+        };
+        assert_eq!(emitted_light(&cold_magma, surface), Radiance::black());
+    }
+}
@@ -1,9 +1,8 @@
+use std::fmt::Debug;
+
 use glm::{Vec2, Vec3};
 
-use crate::{
-    material::Material,
-    texture::CubeFace,
-};
+use crate::{cube::BoxBounds, material::Material, texture::CubeFace};
 
 #[derive(Debug)]
 pub struct Intersect {
@@ -24,6 +23,12 @@ pub const EMPTY_INTERSECT: Intersect = Intersect {
     face: CubeFace::TOP,
 };
 
-pub trait Traceable {
+/// Anything a ray can hit.
+///
+/// Implementors must be shareable between threads since every pixel is traced in parallel.
+pub trait Traceable: Debug + Send + Sync {
     fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Option<Intersect>;
+
+    /// The axis aligned box that fully contains the object.
+    fn bounding_box(&self) -> BoxBounds;
 }
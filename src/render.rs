@@ -1,24 +1,65 @@
-use std::fmt::Debug;
+use std::sync::Arc;
 
 use glm::Vec3;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-use crate::light::{AmbientLightIntensity, Light};
-use crate::texture::GameTextures;
+use crate::accumulation::{AccumulationBuffer, PixelEstimate};
+use crate::integrator::{Integrator, Whitted};
+use crate::light::Light;
+use crate::sampling::{pixel_rng, PixelFilter, SamplePattern};
+use crate::scene::Scene;
+use crate::tonemap::DisplayTransform;
 use crate::Model;
-use crate::{color::Color, framebuffer::Framebuffer};
+use crate::{
+    color::{Color, Radiance},
+    framebuffer::Framebuffer,
+};
 
 use crate::raytracer::{Intersect, Traceable};
+use crate::texture::TextureRegistry;
+
+/// Options that control how a frame is rendered, independent of what's in the scene.
+#[derive(Debug, Clone)]
+pub struct RenderSettings {
+    /// How the computed radiance is converted into displayable colors.
+    pub display: DisplayTransform,
+    /// How many rays are traced for every pixel.
+    pub samples_per_pixel: u32,
+    /// Where inside the footprint of the pixel the rays go through.
+    pub sample_pattern: SamplePattern,
+    /// How the samples of a pixel are combined.
+    pub filter: PixelFilter,
+    /// The algorithm that computes the light arriving through every ray.
+    pub integrator: Arc<dyn Integrator>,
+    /// Seed of the random numbers used while tracing, the same seed gives the same frame.
+    pub seed: u64,
+    /// Seconds since the animations of the textures started.
+    pub time: f32,
+}
 
-pub fn init_render(framebuffer: &mut Framebuffer, data: &Model) {
-    render(framebuffer, data);
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            display: DisplayTransform::default(),
+            samples_per_pixel: 1,
+            sample_pattern: SamplePattern::default(),
+            filter: PixelFilter::default(),
+            integrator: Arc::new(Whitted),
+            seed: 0,
+            time: 0.0,
+        }
+    }
 }
 
-fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
+pub fn init_render(framebuffer: &mut Framebuffer, data: &Model, settings: &RenderSettings) {
+    render(framebuffer, data, settings);
+}
+
+pub(crate) fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
     incident - 2.0 * incident.dot(normal) * normal
 }
 
-fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
+pub(crate) fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
     let cosi = -incident.dot(normal).clamp(-1.0, 1.0);
     // We assume the ray is leaving the object...
     let mut n_cosi = cosi;
@@ -41,284 +82,305 @@ fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
     }
 }
 
-fn cast_shadow<'a, T: Traceable + 'a, ObIterable: Iterator<Item = &'a T>>(
-    intersect: &Intersect,
-    light: &Light,
-    objects: ObIterable,
-) -> f32 {
+fn cast_shadow(intersect: &Intersect, light: &Light, scene: &Scene) -> f32 {
     let light_dir = (light.position - intersect.point).normalize();
-    let shadow_ray_origin = intersect.point;
-    let mut shadow_intensity = 0.0;
-
-    for object in objects {
-        let shadow_intersect = object.ray_intersect(&shadow_ray_origin, &light_dir);
-        if let Some(object_intersection) = shadow_intersect {
-            if object_intersection.distance < 0.0 {
-                break;
-            }
+    // Offset the origin to the side of the surface facing the light to avoid self shadowing.
+    let normal_offset = if light_dir.dot(&intersect.normal) < 0.0 {
+        -intersect.normal
+    } else {
+        intersect.normal
+    };
+    let shadow_ray_origin = intersect.point + 1e-3 * normal_offset;
+    let distance_to_light = nalgebra_glm::distance(&light.position, &shadow_ray_origin);
 
+    match scene.ray_intersect(&shadow_ray_origin, &light_dir) {
+        Some(object_intersection) if object_intersection.distance < distance_to_light => {
             let distance_from_object_to_light =
                 nalgebra_glm::distance2(&light.position, &object_intersection.point);
-            shadow_intensity = object_intersection.distance / distance_from_object_to_light
-                * (1.0 - object_intersection.material.transparency);
-
-            break;
+            object_intersection.distance / distance_from_object_to_light
+                * (1.0 - object_intersection.material.transparency)
         }
+        _ => 0.0,
+    }
+}
+
+/// The color of the surface at the intersection, taken from its texture if it has any.
+/// Animated textures show how they look `time` seconds into their animation.
+pub(crate) fn surface_color(
+    intersect: &Intersect,
+    textures: &TextureRegistry,
+    time: f32,
+) -> Radiance {
+    if let Some(texture_map) = &intersect.material.texture_map {
+        let tint: Radiance = intersect.material.diffuse.into();
+        return tint * Radiance::from(texture_map.sample_at(&intersect.texture_cords, time));
     }
 
-    shadow_intensity
+    match intersect.material.texture {
+        Some(tx_type) => textures
+            .face_color(tx_type, &intersect.face, &intersect.texture_cords, time)
+            .into(),
+        None => intersect.material.diffuse.into(),
+    }
 }
 
-const TARGET_COLOR: u32 = 0x000000;
-pub fn cast_ray<T: Traceable + Eq + Debug>(
+pub fn cast_ray(
     ray_origin: &Vec3,
     ray_direction: &Vec3,
-    objects: &[T],
+    data: &Model,
     lights: &[Light],
-    ambient_light: AmbientLightIntensity,
-    textures: &GameTextures,
-    sky_color: &Color,
+    time: f32,
     depth: u32,
-) -> Color {
+) -> Radiance {
+    let Model {
+        scene,
+        ambient_light,
+        textures,
+        sky_color,
+        ..
+    } = data;
+
     if depth > 3 {
-        return *sky_color;
+        return sky_color.into();
     }
 
-    let (intersect, _, impact_object) = objects
+    let intersect = match scene.ray_intersect(ray_origin, ray_direction) {
+        Some(intersect) => intersect,
+        None => return sky_color.into(),
+    };
+
+    let tx_color = surface_color(&intersect, textures, time);
+    let view_dir = (ray_origin - intersect.point).normalize();
+
+    let direct_light: Radiance = lights
         .iter()
-        .flat_map(|object| {
-            object
-                .ray_intersect(ray_origin, ray_direction)
-                .map(|inter| (inter, object))
+        .map(|current_light| {
+            let light_dir = (current_light.position - intersect.point).normalize();
+            let reflect_dir = reflect(&-light_dir, &intersect.normal).normalize();
+            let shadow_intensity = cast_shadow(&intersect, current_light, scene);
+            let light_intensity = current_light.intensity * (1.0 - shadow_intensity);
+
+            let diffuse_intensity =
+                intersect.normal.dot(&light_dir).clamp(0.0, 1.0) + *ambient_light;
+            let diffuse =
+                tx_color * intersect.material.albedo.0 * diffuse_intensity * light_intensity;
+
+            let specular_intensity = view_dir
+                .dot(&reflect_dir)
+                .clamp(0.0, 1.0)
+                .powf(intersect.material.specular);
+            let specular = Radiance::from(current_light.color)
+                * intersect.material.albedo.1
+                * specular_intensity
+                * light_intensity;
+
+            diffuse + specular
         })
-        .fold((None, f32::INFINITY, None), |accum, intersection| {
-            if intersection.0.distance < accum.1 {
-                let distance = intersection.0.distance;
-                (Some(intersection.0), distance, Some(intersection.1))
-            } else {
-                accum
-            }
-        });
-
-    if let (Some(intersect), Some(impact_object)) = (intersect, impact_object) {
-        lights
-            .iter()
-            .fold(Color::default(), |accumulator_color, current_light| {
-                let light_dir = (current_light.position - intersect.point).normalize();
-                let view_dir = (ray_origin - intersect.point).normalize();
-                let reflect_dir = reflect(&-light_dir, &intersect.normal).normalize();
-                let shadow_intensity = cast_shadow(
-                    &intersect,
-                    current_light,
-                    objects.iter().filter(|o| o != &impact_object),
-                );
-                let light_intensity = current_light.intensity * (1.0 - shadow_intensity);
-                // if light_intensity < 0.0 {
-                //     println!(
-                //         "The light is negative! {} * (1.0 - {})",
-                //         current_light.intensity, shadow_intensity
-                //     )
-                // }
-
-                let diffuse_intensity =
-                    intersect.normal.dot(&light_dir).clamp(0.0, 1.0) + ambient_light;
-                let tx_color = match intersect.material.texture {
-                    Some(tx_type) => {
-                        let texture = textures.get_texture(&tx_type);
-                        texture.get_color_of_face(
-                            &intersect.face,
-                            intersect.texture_cords.x * texture.sprite_size as f32,
-                            intersect.texture_cords.y * texture.sprite_size as f32,
-                        )
-                    }
-                    None => intersect.material.diffuse,
-                };
-                let diffuse =
-                    tx_color * intersect.material.albedo.0 * diffuse_intensity * light_intensity;
-                // if diffuse == TARGET_COLOR.into() {
-                //     println!(
-                //         "Diffuse is black!\n{tx_color:?} * {} * {diffuse_intensity} * {light_intensity}",
-                //          intersect.material.albedo.0,
-                //     )
-                // }
-
-                let specular_intensity = view_dir
-                    .dot(&reflect_dir)
-                    .clamp(0.0, 1.0)
-                    .powf(intersect.material.specular);
-                let specular = current_light.color
-                    * intersect.material.albedo.1
-                    * specular_intensity
-                    * light_intensity;
-
-                let mut reflect_color = Color::black();
-                let reflectivity = intersect.material.reflectivity;
-                if reflectivity > 0.0 {
-                    let reflect_dir = reflect(&-ray_direction, &intersect.normal).normalize();
-                    // Tenemos que hacer offset para evitar el acné
-                    let reflect_origin = intersect.point + 1e-2 * intersect.normal;
-                    reflect_color = cast_ray(
-                        &reflect_origin,
-                        &reflect_dir,
-                        objects,
-                        lights,
-                        ambient_light,
-                        textures,
-                        sky_color,
-                        depth + 1,
-                    )
-                }
-
-                let mut refract_color = Color::black();
-                let transparency = intersect.material.transparency;
-                if transparency > 0.0 {
-                    let refract_dir = refract(
-                        ray_direction,
-                        &intersect.normal,
-                        intersect.material.refractive_index,
-                    );
-                    // Tenemos que hacer offset para evitar el acné
-                    let refract_origin = intersect.point + 1e-3 * intersect.normal;
-
-                    refract_color = cast_ray(
-                        &refract_origin,
-                        &refract_dir,
-                        objects,
-                        lights,
-                        ambient_light,
-                        textures,
-                        sky_color,
-                        depth + 1,
-                    );
-                }
-
-                // if color == TARGET_COLOR.into() {
-                //     println!();
-                //     println!(
-                //         r#"Found target color! {color:?}
-                // DIFFUSE:
-                // intensity: {diffuse_intensity}
-                // light_intensity: {light_intensity}
-                //
-                // REFLECT:
-                // reflectivity: {reflectivity}
-                //
-                // REFRACT:
-                // transparency: {transparency}
-                //
-                // accum: {accumulator_color:?}
-                // + ({diffuse:?} + {specular:?}) * (1.0 - {reflectivity} - {transparency})
-                // + ({reflect_color:?} * {reflectivity})
-                // + ({refract_color:?} * {transparency})
-                //
-                // Intersect: {intersect:#?}
-                // ImpactObject: {impact_object:#?}
-                // CurrentLight: {current_light:#?}
-                // "#
-                //     );
-                // }
-
-                accumulator_color
-                    + (diffuse + specular) * (1.0 - reflectivity - transparency)
-                    + (reflect_color * reflectivity)
-                    + (refract_color * transparency)
-            })
-    } else {
-        *sky_color
+        .sum();
+
+    let mut reflect_color = Radiance::black();
+    let reflectivity = intersect.material.reflectivity;
+    if reflectivity > 0.0 {
+        let reflect_dir = reflect(&-ray_direction, &intersect.normal).normalize();
+        // Tenemos que hacer offset para evitar el acné
+        let reflect_origin = intersect.point + 1e-2 * intersect.normal;
+        reflect_color = cast_ray(&reflect_origin, &reflect_dir, data, lights, time, depth + 1);
     }
+
+    let mut refract_color = Radiance::black();
+    let transparency = intersect.material.transparency;
+    if transparency > 0.0 {
+        let refract_dir = refract(
+            ray_direction,
+            &intersect.normal,
+            intersect.material.refractive_index,
+        );
+        // Tenemos que hacer offset para evitar el acné
+        let refract_origin = intersect.point + 1e-3 * intersect.normal;
+        refract_color = cast_ray(&refract_origin, &refract_dir, data, lights, time, depth + 1);
+    }
+
+    direct_light * (1.0 - reflectivity - transparency)
+        + reflect_color * reflectivity
+        + refract_color * transparency
 }
 
+/// Distance in pixels between the rays of `animated_textures_visible`.
+const ANIMATION_PROBE_STEP: usize = 8;
+
 pub const DAY_COLOR: Color = Color::new(0x87, 0xCE, 0xEB);
 pub const NIGHT_COLOR: Color = Color::new(0x03, 0x03, 0x05);
 
-pub fn render(framebuffer: &mut Framebuffer, data: &Model) {
-    framebuffer.clear();
-
-    let width = framebuffer.width as f32;
-    let height = framebuffer.height as f32;
+/// Computes the direction of the camera ray that goes through the given point of the image.
+///
+/// `pixel_x` and `pixel_y` are in pixels, (0, 0) being the top left corner of the image.
+fn primary_ray_direction(
+    data: &Model,
+    pixel_x: f32,
+    pixel_y: f32,
+    width: f32,
+    height: f32,
+) -> Vec3 {
     let aspect_ratio = width / height;
+    let fov_scale = (data.camera.fov / 2.0).tan();
+
+    // Map the pixel coordinate to screen space [-1, 1]
+    let screen_x = (2.0 * pixel_x) / width - 1.0;
+    let screen_y = -(2.0 * pixel_y) / height + 1.0;
+
+    // Adjust for aspect ratio
+    let screen_x = screen_x * aspect_ratio * fov_scale;
+    let screen_y = screen_y * fov_scale;
+
+    // Calculate the direction of the ray for this pixel
+    let ray_direction = Vec3::new(screen_x, screen_y, -1.0).normalize();
+    data.camera.change_basis(&ray_direction)
+}
+
+/// Traces all the samples of a pixel and weights them with the filter of the settings.
+///
+/// The random numbers come from the seed of the settings, the pass and the pixel.
+/// In the first pass a single sample goes through the center of the pixel,
+/// that way frames rendered with one sample per pixel don't flicker.
+fn render_pixel(
+    x: usize,
+    y: usize,
+    framebuffer_size: (f32, f32),
+    data: &Model,
+    lights: &[Light],
+    settings: &RenderSettings,
+    pass: u32,
+) -> PixelEstimate {
+    let (width, height) = framebuffer_size;
+    let filter = settings.filter;
+    let mut rng = pixel_rng(settings.seed, pass, y * width as usize + x);
+    let offsets = if pass == 0 && settings.samples_per_pixel <= 1 {
+        vec![(0.0, 0.0)]
+    } else {
+        settings.sample_pattern.offsets(
+            settings.samples_per_pixel.max(1),
+            filter.radius(),
+            &mut rng,
+        )
+    };
+
+    let mut estimate = PixelEstimate::default();
+    for (dx, dy) in offsets {
+        // Samples are placed around the center of the pixel
+        let pixel_x = x as f32 + 0.5 + dx;
+        let pixel_y = y as f32 + 0.5 + dy;
+
+        let ray_direction = primary_ray_direction(data, pixel_x, pixel_y, width, height);
+        let sample = settings.integrator.radiance(
+            &data.camera.eye,
+            &ray_direction,
+            data,
+            lights,
+            settings.time,
+            &mut rng,
+        );
+        estimate.add_sample(sample, filter.weight(dx, dy));
+    }
+
+    estimate
+}
+
+/// Traces every pixel of a frame of the given size, `pass` tells apart the frames accumulated
+/// into the same image.
+fn render_pass(
+    width: usize,
+    height: usize,
+    data: &Model,
+    settings: &RenderSettings,
+    pass: u32,
+) -> Vec<PixelEstimate> {
+    let framebuffer_size = (width as f32, height as f32);
 
     let mut lights = Vec::with_capacity(data.lights.len());
     lights.append(&mut data.lights.to_vec());
     lights.push(data.top_light.clone());
 
-    let sphere_lights = &lights;
-    let cube_lights = &lights;
-
-    let sphere_colors: Vec<Color> = (0..framebuffer.height)
+    (0..height)
         .into_par_iter()
         .flat_map(|y| {
-            (0..framebuffer.width).into_par_iter().map(move |x| {
-                // Map the pixel coordinate to screen space [-1, 1]
-                let screen_x = (2.0 * x as f32) / width - 1.0;
-                let screen_y = -(2.0 * y as f32) / height + 1.0;
-
-                // Adjust for aspect ratio
-                let screen_x = screen_x * aspect_ratio;
-
-                // Calculate the direction of the ray for this pixel
-                let ray_direction = Vec3::new(screen_x, screen_y, -1.0).normalize();
-
-                // Cast the ray and get the pixel color
-                let rotated_direction = data.camera.change_basis(&ray_direction);
-                cast_ray(
-                    &data.camera.eye,
-                    &rotated_direction,
-                    &data.spheres,
-                    sphere_lights,
-                    1.0,
-                    &data.textures,
-                    &data.sky_color,
-                    0,
-                )
-            })
+            let lights = &lights;
+            (0..width)
+                .into_par_iter()
+                .map(move |x| render_pixel(x, y, framebuffer_size, data, lights, settings, pass))
         })
-        .collect();
+        .collect()
+}
 
-    let cube_colors: Vec<Color> = (0..framebuffer.height)
-        .into_par_iter()
+/// Whether the camera sees any surface with an animated texture, that is, whether the frame
+/// changes with `RenderSettings::time`.
+///
+/// Only one ray every `ANIMATION_PROBE_STEP` pixels is traced so it's much cheaper than a frame,
+/// surfaces smaller than that or only seen in reflections may be missed.
+pub fn animated_textures_visible(data: &Model, width: usize, height: usize) -> bool {
+    let (width_f, height_f) = (width as f32, height as f32);
+    (0..height)
+        .step_by(ANIMATION_PROBE_STEP)
         .flat_map(|y| {
-            (0..framebuffer.width).into_par_iter().map(move |x| {
-                // Map the pixel coordinate to screen space [-1, 1]
-                let screen_x = (2.0 * x as f32) / width - 1.0;
-                let screen_y = -(2.0 * y as f32) / height + 1.0;
-
-                // Adjust for aspect ratio
-                let screen_x = screen_x * aspect_ratio;
-
-                // Calculate the direction of the ray for this pixel
-                let ray_direction = Vec3::new(screen_x, screen_y, -1.0).normalize();
-
-                // Cast the ray and get the pixel color
-                let rotated_direction = data.camera.change_basis(&ray_direction);
-                cast_ray(
-                    &data.camera.eye,
-                    &rotated_direction,
-                    &data.cubes,
-                    cube_lights,
-                    data.ambient_light,
-                    &data.textures,
-                    &data.sky_color,
-                    0,
-                )
-            })
+            (0..width)
+                .step_by(ANIMATION_PROBE_STEP)
+                .map(move |x| (x, y))
         })
-        .collect();
+        .any(|(x, y)| {
+            let ray_direction =
+                primary_ray_direction(data, x as f32 + 0.5, y as f32 + 0.5, width_f, height_f);
+            data.scene
+                .ray_intersect(&data.camera.eye, &ray_direction)
+                .is_some_and(|intersect| intersect.material.is_animated(&data.textures))
+        })
+}
+
+/// Converts the radiance of every pixel into colors and paints them.
+fn present(framebuffer: &mut Framebuffer, pixel_radiances: &[Radiance], settings: &RenderSettings) {
+    framebuffer.clear();
 
-    let pixel_colors: Vec<Color> = cube_colors
-        .into_iter()
-        .zip(sphere_colors)
-        .map(|(c, s)| if s == data.sky_color { c } else { s })
-        .collect();
+    let pixel_colors = settings.display.apply(pixel_radiances);
 
     for (i, color) in pixel_colors.into_iter().enumerate() {
         framebuffer.set_current_color(color);
         let y = (i / framebuffer.width) as f32;
-        let x = if i > framebuffer.width {
-            i % framebuffer.width
-        } else {
-            i
-        } as f32;
+        let x = (i % framebuffer.width) as f32;
         let _ = framebuffer.paint_point(nalgebra_glm::Vec2::new(x, y));
     }
 }
+
+pub fn render(framebuffer: &mut Framebuffer, data: &Model, settings: &RenderSettings) {
+    let pixel_radiances: Vec<Radiance> =
+        render_pass(framebuffer.width, framebuffer.height, data, settings, 0)
+            .iter()
+            .map(PixelEstimate::resolve)
+            .collect();
+
+    present(framebuffer, &pixel_radiances, settings);
+}
+
+/// Renders one more pass of samples into `accumulation` and paints the average of every
+/// pass so far.
+///
+/// The first pass after a reset behaves like `render`, the rest place their samples at
+/// random so the image keeps getting smoother while nothing changes.
+pub fn render_progressive(
+    framebuffer: &mut Framebuffer,
+    accumulation: &mut AccumulationBuffer,
+    data: &Model,
+    settings: &RenderSettings,
+) {
+    if accumulation.width != framebuffer.width || accumulation.height != framebuffer.height {
+        *accumulation = AccumulationBuffer::new(framebuffer.width, framebuffer.height);
+    }
+
+    let pass = render_pass(
+        framebuffer.width,
+        framebuffer.height,
+        data,
+        settings,
+        accumulation.passes(),
+    );
+    accumulation.add_pass(&pass);
+
+    present(framebuffer, &accumulation.resolve(), settings);
+}
@@ -0,0 +1,697 @@
+//! Loads the block textures of Minecraft resource packs.
+//!
+//! A resource pack is a directory with a `pack.mcmeta` file and its assets under
+//! `assets/<namespace>/`. Every image of `textures/block/` (`textures/blocks/` in old packs)
+//! is registered as `block/<name>`, or `<namespace>:block/<name>` outside of the `minecraft`
+//! namespace. Images with a `.mcmeta` file are animated, vertical strips of frames without one
+//! use their first frame.
+//!
+//! Blocks get a texture per face named like the block (e.g. `grass_block`) from their
+//! `blockstates/` and `models/` JSON. The vanilla parent models (`block/cube_all`,
+//! `block/cube_column`, ...) are built in, so packs only need the models they change. Faces
+//! with a `tintindex` are tinted with the color of plains grass.
+//!
+//! Packs that only have textures, like most of them, get their blocks from the names of the
+//! images: `grass_block_top`, `grass_block_side`, `oak_log_top`, `furnace_front`, ...
+//! Only the first variant of a block is used and the rotations of its models are ignored.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use image::{GenericImageView, ImageError, ImageReader};
+use serde::Deserialize;
+
+use crate::{
+    color::Color,
+    texture::{BlockFaces, Texture, TextureError, TextureId, TextureRegistry},
+};
+
+const PACK_FILE: &str = "pack.mcmeta";
+
+/// Color of the faces with a tint, the grass of plains.
+const TINT: Color = Color::new(0x91, 0xbd, 0x59);
+
+/// Blocks whose bottom is dirt instead of an image named like them.
+const DIRT_BOTTOMS: [&str; 4] = ["grass_block", "mycelium", "podzol", "dirt_path"];
+
+/// Suffixes of the images of the faces of a block, used when it has no model.
+const FACE_SUFFIXES: [&str; 5] = ["_top", "_bottom", "_side", "_front", "_end"];
+
+/// The vanilla models that most block models inherit from.
+const BUILTIN_MODELS: [(&str, &str); 10] = [
+    ("block/block", "{}"),
+    (
+        "block/cube",
+        r##"{ "parent": "block/block", "elements": [{ "faces": {
+            "down": { "texture": "#down" }, "up": { "texture": "#up" },
+            "north": { "texture": "#north" }, "south": { "texture": "#south" },
+            "west": { "texture": "#west" }, "east": { "texture": "#east" } } }] }"##,
+    ),
+    (
+        "block/cube_all",
+        r##"{ "parent": "block/cube", "textures": {
+            "down": "#all", "up": "#all", "north": "#all",
+            "south": "#all", "west": "#all", "east": "#all" } }"##,
+    ),
+    (
+        "block/cube_mirrored_all",
+        r##"{ "parent": "block/cube_all" }"##,
+    ),
+    (
+        "block/cube_column",
+        r##"{ "parent": "block/cube", "textures": {
+            "down": "#end", "up": "#end", "north": "#side",
+            "south": "#side", "west": "#side", "east": "#side" } }"##,
+    ),
+    (
+        "block/cube_bottom_top",
+        r##"{ "parent": "block/cube", "textures": {
+            "down": "#bottom", "up": "#top", "north": "#side",
+            "south": "#side", "west": "#side", "east": "#side" } }"##,
+    ),
+    (
+        "block/cube_top",
+        r##"{ "parent": "block/cube", "textures": {
+            "down": "#side", "up": "#top", "north": "#side",
+            "south": "#side", "west": "#side", "east": "#side" } }"##,
+    ),
+    (
+        "block/orientable_with_bottom",
+        r##"{ "parent": "block/cube", "textures": {
+            "down": "#bottom", "up": "#top", "north": "#front",
+            "south": "#side", "west": "#side", "east": "#side" } }"##,
+    ),
+    (
+        "block/orientable",
+        r##"{ "parent": "block/orientable_with_bottom", "textures": { "bottom": "#top" } }"##,
+    ),
+    (
+        "block/leaves",
+        r##"{ "parent": "block/block", "elements": [{ "faces": {
+            "down": { "texture": "#all", "tintindex": 0 },
+            "up": { "texture": "#all", "tintindex": 0 },
+            "north": { "texture": "#all", "tintindex": 0 },
+            "south": { "texture": "#all", "tintindex": 0 },
+            "west": { "texture": "#all", "tintindex": 0 },
+            "east": { "texture": "#all", "tintindex": 0 } } }] }"##,
+    ),
+];
+
+/// How deep the parents of a model or the references of its textures can go.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug)]
+pub enum ResourcePackError {
+    NotAPack(PathBuf),
+    Io(PathBuf, io::Error),
+    Json(PathBuf, serde_json::Error),
+}
+
+impl Display for ResourcePackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourcePackError::NotAPack(path) => {
+                write!(f, "{} has no {PACK_FILE}", path.display())
+            }
+            ResourcePackError::Io(path, error) => {
+                write!(f, "Couldn't read {}: {error}", path.display())
+            }
+            ResourcePackError::Json(path, error) => {
+                write!(f, "Invalid JSON in {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResourcePackError {}
+
+/// What `pack.mcmeta` says about the pack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackInfo {
+    pub description: String,
+    pub pack_format: u32,
+}
+
+#[derive(Deserialize)]
+struct PackMeta {
+    pack: PackSection,
+}
+
+#[derive(Deserialize)]
+struct PackSection {
+    pack_format: u32,
+    #[serde(default)]
+    description: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct BlockstateDef {
+    variants: Option<BTreeMap<String, VariantDef>>,
+    multipart: Option<Vec<MultipartDef>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum VariantDef {
+    One(ModelRef),
+    /// Models picked at random, the first one is used.
+    Many(Vec<ModelRef>),
+}
+
+impl VariantDef {
+    fn model(&self) -> Option<&str> {
+        match self {
+            VariantDef::One(model) => Some(&model.model),
+            VariantDef::Many(models) => models.first().map(|model| model.model.as_str()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ModelRef {
+    model: String,
+}
+
+#[derive(Deserialize)]
+struct MultipartDef {
+    apply: VariantDef,
+}
+
+#[derive(Deserialize, Clone)]
+struct ModelDef {
+    parent: Option<String>,
+    #[serde(default)]
+    textures: HashMap<String, String>,
+    elements: Option<Vec<ElementDef>>,
+}
+
+#[derive(Deserialize, Clone)]
+struct ElementDef {
+    #[serde(default)]
+    faces: HashMap<String, FaceDef>,
+}
+
+#[derive(Deserialize, Clone)]
+struct FaceDef {
+    texture: String,
+    tintindex: Option<i32>,
+}
+
+/// Whether `dir` is a resource pack, that is, has a `pack.mcmeta`.
+pub fn is_resource_pack(dir: impl AsRef<Path>) -> bool {
+    dir.as_ref().join(PACK_FILE).is_file()
+}
+
+/// Loads the block textures of the resource pack at `dir` into `textures`, replacing the
+/// ones with the same names. See the module documentation for the names they get.
+///
+/// Images that can't be read become `Texture::missing` and blocks that use models or
+/// textures the pack doesn't have keep their textures, both with a warning.
+pub fn load_resource_pack(
+    textures: &mut TextureRegistry,
+    dir: impl AsRef<Path>,
+) -> Result<PackInfo, ResourcePackError> {
+    let dir = dir.as_ref();
+    if !is_resource_pack(dir) {
+        return Err(ResourcePackError::NotAPack(dir.to_path_buf()));
+    }
+    let meta: PackMeta = read_json(&dir.join(PACK_FILE))?;
+    let info = PackInfo {
+        description: text_component(&meta.pack.description),
+        pack_format: meta.pack.pack_format,
+    };
+
+    let assets = dir.join("assets");
+    let mut loader = Loader {
+        assets: assets.clone(),
+        textures,
+        images: BTreeSet::new(),
+        models: HashMap::new(),
+    };
+    let mut skipped = vec![];
+    for namespace in sorted_entries(&assets)? {
+        if !namespace.is_dir() {
+            continue;
+        }
+        let namespace = file_name(&namespace);
+        loader.load_images(&namespace)?;
+
+        let mut blocks = BTreeSet::new();
+        let blockstates = assets.join(&namespace).join("blockstates");
+        for file in sorted_entries(&blockstates)? {
+            if file.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let block = resource_name(&namespace, &texture_stem(&file));
+            let faces = read_json(&file)
+                .ok()
+                .and_then(|blockstate| loader.blockstate_faces(&namespace, &blockstate))
+                .or_else(|| loader.conventional_faces(&block));
+            match faces {
+                Some(faces) => {
+                    loader.textures.insert_block(&block, faces);
+                }
+                None => skipped.push(block.clone()),
+            }
+            blocks.insert(block);
+        }
+
+        // Packs with only textures
+        let prefix = resource_name(&namespace, "block/");
+        let bases: BTreeSet<String> = loader
+            .images
+            .iter()
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .map(|name| {
+                let base = FACE_SUFFIXES
+                    .iter()
+                    .find_map(|suffix| name.strip_suffix(suffix))
+                    .unwrap_or(name);
+                resource_name(&namespace, base)
+            })
+            .filter(|block| !blocks.contains(block))
+            .collect();
+        for block in bases {
+            if let Some(faces) = loader.conventional_faces(&block) {
+                loader.textures.insert_block(&block, faces);
+            }
+        }
+    }
+
+    if !skipped.is_empty() {
+        eprintln!(
+            "Warning: {} blocks of the resource pack {} use models or textures it doesn't have, \
+             they keep their default textures: {}",
+            skipped.len(),
+            dir.display(),
+            skipped.join(", ")
+        );
+    }
+
+    Ok(info)
+}
+
+struct Loader<'a> {
+    assets: PathBuf,
+    textures: &'a mut TextureRegistry,
+    /// The names of the images of the pack, the only ones that get tinted.
+    images: BTreeSet<String>,
+    models: HashMap<String, Option<ModelDef>>,
+}
+
+impl Loader<'_> {
+    /// Registers the block images of a namespace.
+    fn load_images(&mut self, namespace: &str) -> Result<(), ResourcePackError> {
+        let textures = self.assets.join(namespace).join("textures");
+        for folder in ["blocks", "block"] {
+            for file in sorted_entries(&textures.join(folder))? {
+                if file.extension().is_none_or(|extension| extension != "png") {
+                    continue;
+                }
+                let name = resource_name(namespace, &format!("block/{}", texture_stem(&file)));
+                let texture = read_face_texture(&file).unwrap_or_else(|error| {
+                    eprintln!("Warning: using a placeholder for the `{name}` texture. {error}");
+                    Texture::missing()
+                });
+                self.textures.insert(&name, texture);
+                self.images.insert(name);
+            }
+        }
+        Ok(())
+    }
+
+    /// The faces of the first variant of a block.
+    fn blockstate_faces(
+        &mut self,
+        namespace: &str,
+        blockstate: &BlockstateDef,
+    ) -> Option<BlockFaces> {
+        let variant = match (&blockstate.variants, &blockstate.multipart) {
+            (Some(variants), _) => ["", "normal"]
+                .iter()
+                .find_map(|key| variants.get(*key))
+                .or_else(|| {
+                    variants
+                        .iter()
+                        .find(|(key, _)| key.contains("axis=y"))
+                        .map(|(_, variant)| variant)
+                })
+                .or_else(|| variants.values().next()),
+            (None, Some(parts)) => parts.first().map(|part| &part.apply),
+            (None, None) => None,
+        }?;
+        let model = qualified(namespace, variant.model()?);
+        self.model_faces(&model)
+    }
+
+    /// The faces of a model, from the first of its elements that has each of them.
+    fn model_faces(&mut self, model: &str) -> Option<BlockFaces> {
+        let mut textures = HashMap::new();
+        let mut elements = None;
+        let mut next = Some(model.to_string());
+        for _ in 0..MAX_DEPTH {
+            let Some(name) = next.take() else { break };
+            let def = self.model(&name)?;
+            for (variable, value) in &def.textures {
+                textures
+                    .entry(variable.clone())
+                    .or_insert_with(|| value.clone());
+            }
+            if elements.is_none() {
+                elements = def.elements.clone();
+            }
+            let namespace = namespace_of(&name).to_string();
+            next = def
+                .parent
+                .as_ref()
+                .map(|parent| qualified(&namespace, parent));
+        }
+
+        let elements = elements?;
+        let mut face = |direction: &str| {
+            let face = elements
+                .iter()
+                .find_map(|element| element.faces.get(direction))?;
+            let texture = resolve_variable(&textures, &face.texture)?;
+            self.face_texture(&texture, face.tintindex.is_some())
+        };
+        Some(BlockFaces {
+            up: face("up")?,
+            down: face("down")?,
+            north: face("north")?,
+            south: face("south")?,
+            west: face("west")?,
+            east: face("east")?,
+        })
+    }
+
+    /// A model by its qualified name, read from the pack or built in.
+    fn model(&mut self, name: &str) -> Option<ModelDef> {
+        if !self.models.contains_key(name) {
+            let (namespace, path) = split_namespace(name);
+            let file = self
+                .assets
+                .join(namespace)
+                .join("models")
+                .join(format!("{}.json", normalize_path(path)));
+            let def = match file.is_file() {
+                true => read_json(&file).ok(),
+                false => BUILTIN_MODELS
+                    .iter()
+                    .find(|(builtin, _)| namespace == "minecraft" && *builtin == path)
+                    .and_then(|(_, source)| serde_json::from_str(source).ok()),
+            };
+            self.models.insert(name.to_string(), def);
+        }
+        self.models[name].clone()
+    }
+
+    /// The id of a texture of the pack (or already registered), tinted if asked.
+    fn face_texture(&mut self, texture: &str, tinted: bool) -> Option<TextureId> {
+        let (namespace, path) = split_namespace(texture);
+        let name = resource_name(namespace, &normalize_path(path));
+        if !tinted {
+            return self.textures.id(&name);
+        }
+
+        let tinted_name = format!("{name}#tinted");
+        if let Some(id) = self.textures.id(&tinted_name) {
+            return Some(id);
+        }
+        let id = self.textures.id(&name)?;
+        match self.images.contains(&name) {
+            true => {
+                let texture = self.textures.get(id).tinted(TINT);
+                Some(self.textures.insert(&tinted_name, texture))
+            }
+            false => Some(id),
+        }
+    }
+
+    /// The faces of a block from the names of the images of the pack, like `oak_log_top`.
+    fn conventional_faces(&mut self, block: &str) -> Option<BlockFaces> {
+        let (namespace, base) = split_namespace(block);
+        let prefix = resource_name(namespace, "block/");
+        let dirt = match namespace == "minecraft" && DIRT_BOTTOMS.contains(&base) {
+            true => self.textures.id("block/dirt"),
+            false => None,
+        };
+        let mut find = |suffixes: &[&str]| {
+            suffixes.iter().find_map(|suffix| {
+                let texture = format!("{prefix}{base}{suffix}");
+                let tinted = texture.ends_with("grass_block_top") || texture.ends_with("_leaves");
+                self.face_texture(&texture, tinted)
+            })
+        };
+
+        let side = find(&["_side", "", "_top", "_end", "_front"])?;
+        let top = find(&["_top", "_end", ""]).unwrap_or(side);
+        let front = find(&["_front"]).unwrap_or(side);
+        let bottom = match dirt {
+            Some(dirt) => find(&["_bottom"]).unwrap_or(dirt),
+            None => find(&["_bottom", "_end", ""]).unwrap_or(top),
+        };
+
+        Some(BlockFaces {
+            up: top,
+            down: bottom,
+            north: front,
+            south: side,
+            west: side,
+            east: side,
+        })
+    }
+}
+
+/// Follows the `#variable` references of a model texture up to a texture name.
+fn resolve_variable(textures: &HashMap<String, String>, texture: &str) -> Option<String> {
+    let mut texture = texture;
+    for _ in 0..MAX_DEPTH {
+        match texture.strip_prefix('#') {
+            Some(variable) => texture = textures.get(variable)?,
+            None => return Some(texture.to_string()),
+        }
+    }
+    None
+}
+
+/// The plain text of a JSON text component, like the description of a pack.
+fn text_component(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(parts) => parts.iter().map(text_component).collect(),
+        serde_json::Value::Object(component) => {
+            let text = component
+                .get("text")
+                .map(text_component)
+                .unwrap_or_default();
+            let extra = component
+                .get("extra")
+                .map(text_component)
+                .unwrap_or_default();
+            text + &extra
+        }
+        _ => String::new(),
+    }
+}
+
+/// Reads the image of a face, animated by its `.mcmeta` file if it has one.
+/// Vertical strips of frames without one are cropped to their first frame.
+fn read_face_texture(path: &Path) -> Result<Texture, TextureError> {
+    let mut mcmeta = path.as_os_str().to_owned();
+    mcmeta.push(".mcmeta");
+    if Path::new(&mcmeta).is_file() {
+        return Texture::load(path);
+    }
+
+    let decode_error = |error| TextureError::Decode(path.to_path_buf(), error);
+    let image = ImageReader::open(path)
+        .map_err(|error| decode_error(ImageError::IoError(error)))?
+        .decode()
+        .map_err(decode_error)?;
+    let (width, height) = image.dimensions();
+    let image = match height > width && height % width == 0 {
+        true => image.crop_imm(0, 0, width, width),
+        false => image,
+    };
+    let colors = image
+        .to_rgb8()
+        .pixels()
+        .map(|pixel| Color::new(pixel[0], pixel[1], pixel[2]))
+        .collect();
+    Ok(Texture::from_colors(image.width(), image.height(), colors))
+}
+
+/// The namespace and path of a resource location, `minecraft` if it has none.
+fn split_namespace(location: &str) -> (&str, &str) {
+    location.split_once(':').unwrap_or(("minecraft", location))
+}
+
+fn namespace_of(location: &str) -> &str {
+    split_namespace(location).0
+}
+
+/// A resource location with its namespace, taking `namespace` if it has none.
+fn qualified(namespace: &str, location: &str) -> String {
+    match location.contains(':') {
+        true => location.to_string(),
+        false => format!("{namespace}:{location}"),
+    }
+}
+
+/// The name the registry gives to a resource, without the namespace for `minecraft`.
+fn resource_name(namespace: &str, path: &str) -> String {
+    match namespace {
+        "minecraft" => path.to_string(),
+        _ => format!("{namespace}:{path}"),
+    }
+}
+
+/// Old packs keep blocks under `blocks/` instead of `block/`.
+fn normalize_path(path: &str) -> String {
+    match path.strip_prefix("blocks/") {
+        Some(rest) => format!("block/{rest}"),
+        None => path.to_string(),
+    }
+}
+
+fn texture_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, ResourcePackError> {
+    let source =
+        fs::read_to_string(path).map_err(|e| ResourcePackError::Io(path.to_path_buf(), e))?;
+    serde_json::from_str(&source).map_err(|e| ResourcePackError::Json(path.to_path_buf(), e))
+}
+
+/// The entries of a directory sorted by path, a missing directory has none.
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>, ResourcePackError> {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect(),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => vec![],
+        Err(error) => return Err(ResourcePackError::Io(dir.to_path_buf(), error)),
+    };
+    entries.sort();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::CubeFace;
+    use glm::Vec2;
+    use image::{Rgb, RgbImage};
+
+    fn write_image(path: &Path, width: u32, height: u32, color: [u8; 3]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        RgbImage::from_pixel(width, height, Rgb(color))
+            .save(path)
+            .unwrap();
+    }
+
+    fn write_file(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn face_color(textures: &TextureRegistry, block: &str, face: CubeFace) -> Color {
+        let id = textures.id(block).unwrap();
+        textures.face_color(id, &face, &Vec2::new(0.5, 0.5), 0.0)
+    }
+
+    #[test]
+    fn test_models_map_faces_to_textures() {
+        let dir = std::env::temp_dir().join("raytracer_test_resource_pack_models");
+        let _ = fs::remove_dir_all(&dir);
+        write_file(
+            &dir.join(PACK_FILE),
+            r#"{ "pack": { "pack_format": 15, "description": { "text": "Test pack" } } }"#,
+        );
+        let textures = dir.join("assets/minecraft/textures/block");
+        write_image(&textures.join("log_top.png"), 4, 4, [200, 0, 0]);
+        // An animation strip of two frames
+        write_image(&textures.join("log_side.png"), 4, 8, [0, 0, 200]);
+        write_file(
+            &dir.join("assets/minecraft/blockstates/log.json"),
+            r#"{ "variants": { "axis=x": { "model": "block/log_x" },
+                               "axis=y": { "model": "minecraft:block/log" } } }"#,
+        );
+        write_file(
+            &dir.join("assets/minecraft/models/block/log.json"),
+            r#"{ "parent": "minecraft:block/cube_column",
+                 "textures": { "end": "block/log_top", "side": "block/log_side" } }"#,
+        );
+
+        let mut registry = TextureRegistry::default();
+        let info = load_resource_pack(&mut registry, &dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(info.description, "Test pack");
+        assert_eq!(info.pack_format, 15);
+        assert_eq!(
+            registry.get(registry.id("block/log_side").unwrap()).height,
+            4
+        );
+        assert_eq!(
+            face_color(&registry, "log", CubeFace::TOP),
+            Color::new(200, 0, 0)
+        );
+        assert_eq!(
+            face_color(&registry, "log", CubeFace::BOTTOM),
+            Color::new(200, 0, 0)
+        );
+        assert_eq!(
+            face_color(&registry, "log", CubeFace::LEFT),
+            Color::new(0, 0, 200)
+        );
+    }
+
+    #[test]
+    fn test_textures_without_models_use_their_names() {
+        let dir = std::env::temp_dir().join("raytracer_test_resource_pack_names");
+        let _ = fs::remove_dir_all(&dir);
+        write_file(
+            &dir.join(PACK_FILE),
+            r#"{ "pack": { "pack_format": 4, "description": "Old pack" } }"#,
+        );
+        let textures = dir.join("assets/minecraft/textures/blocks");
+        write_image(&textures.join("dirt.png"), 2, 2, [100, 50, 0]);
+        write_image(&textures.join("grass_block_top.png"), 2, 2, [255, 255, 255]);
+        write_image(&textures.join("grass_block_side.png"), 2, 2, [0, 100, 0]);
+
+        let mut registry = TextureRegistry::default();
+        load_resource_pack(&mut registry, &dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(face_color(&registry, "grass_block", CubeFace::TOP), TINT);
+        assert_eq!(
+            face_color(&registry, "grass_block", CubeFace::FORWARDS),
+            Color::new(0, 100, 0)
+        );
+        assert_eq!(
+            face_color(&registry, "grass_block", CubeFace::BOTTOM),
+            Color::new(100, 50, 0)
+        );
+        // Replaces the default dirt
+        assert_eq!(registry.id("dirt"), Some(TextureId::DIRT));
+        assert_eq!(
+            face_color(&registry, "dirt", CubeFace::RIGHT),
+            Color::new(100, 50, 0)
+        );
+    }
+}
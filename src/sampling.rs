@@ -0,0 +1,249 @@
+use glm::Vec3;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::f32::consts::PI;
+
+/// Falloff of the Gaussian filter, bigger values give sharper images.
+const GAUSSIAN_ALPHA: f32 = 2.0;
+/// Mitchell-Netravali parameters, 1/3 and 1/3 is the combination the paper recommends.
+const MITCHELL_B: f32 = 1.0 / 3.0;
+const MITCHELL_C: f32 = 1.0 / 3.0;
+
+/// Reconstruction filter used to combine the samples taken around a pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFilter {
+    /// Every sample inside the pixel weights the same.
+    #[default]
+    Box,
+    /// Weights decrease linearly with the distance to the center of the pixel.
+    Tent,
+    Gaussian,
+    /// Mitchell-Netravali cubic, sharper than the Gaussian thanks to its negative lobes.
+    Mitchell,
+}
+
+impl PixelFilter {
+    /// Gets a filter by its name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "box" => Some(PixelFilter::Box),
+            "tent" | "triangle" => Some(PixelFilter::Tent),
+            "gaussian" => Some(PixelFilter::Gaussian),
+            "mitchell" => Some(PixelFilter::Mitchell),
+            _ => None,
+        }
+    }
+
+    /// The filter that comes after this one, useful to cycle through all of them.
+    pub fn next(&self) -> Self {
+        match self {
+            PixelFilter::Box => PixelFilter::Tent,
+            PixelFilter::Tent => PixelFilter::Gaussian,
+            PixelFilter::Gaussian => PixelFilter::Mitchell,
+            PixelFilter::Mitchell => PixelFilter::Box,
+        }
+    }
+
+    /// How far from the center of the pixel, in pixels, the filter has any weight.
+    pub fn radius(&self) -> f32 {
+        match self {
+            PixelFilter::Box => 0.5,
+            PixelFilter::Tent => 1.0,
+            PixelFilter::Gaussian => 1.5,
+            PixelFilter::Mitchell => 2.0,
+        }
+    }
+
+    /// Weight of a sample at the given offset from the center of the pixel.
+    pub fn weight(&self, dx: f32, dy: f32) -> f32 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(&self, x: f32) -> f32 {
+        let radius = self.radius();
+        let x = x.abs();
+        if x > radius {
+            return 0.0;
+        }
+
+        match self {
+            PixelFilter::Box => 1.0,
+            PixelFilter::Tent => radius - x,
+            PixelFilter::Gaussian => {
+                (-GAUSSIAN_ALPHA * x * x).exp() - (-GAUSSIAN_ALPHA * radius * radius).exp()
+            }
+            PixelFilter::Mitchell => mitchell(x * 2.0 / radius),
+        }
+    }
+}
+
+fn mitchell(x: f32) -> f32 {
+    let (b, c) = (MITCHELL_B, MITCHELL_C);
+    let x2 = x * x;
+    let x3 = x2 * x;
+
+    let value = if x < 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x3 + (-18.0 + 12.0 * b + 6.0 * c) * x2 + (6.0 - 2.0 * b)
+    } else {
+        (-b - 6.0 * c) * x3
+            + (6.0 * b + 30.0 * c) * x2
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c)
+    };
+    value / 6.0
+}
+
+/// How the positions of the samples inside the footprint of a pixel are chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SamplePattern {
+    /// Every sample is placed at random.
+    Jittered,
+    /// The footprint is split in a grid and every cell gets a sample placed at random inside it.
+    #[default]
+    Stratified,
+}
+
+impl SamplePattern {
+    /// Gets a pattern by its name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "jittered" | "random" => Some(SamplePattern::Jittered),
+            "stratified" => Some(SamplePattern::Stratified),
+            _ => None,
+        }
+    }
+
+    /// Generates `count` offsets from the center of a pixel inside `[-radius, radius]` on both axis.
+    pub fn offsets<R: Rng + ?Sized>(
+        &self,
+        count: u32,
+        radius: f32,
+        rng: &mut R,
+    ) -> Vec<(f32, f32)> {
+        let mut offsets = Vec::with_capacity(count as usize);
+        let stratified_side = match self {
+            SamplePattern::Jittered => 0,
+            SamplePattern::Stratified => (count as f32).sqrt().floor() as u32,
+        };
+
+        let cell_size = 1.0 / stratified_side.max(1) as f32;
+        for i in 0..stratified_side {
+            for j in 0..stratified_side {
+                let u = (i as f32 + rng.gen::<f32>()) * cell_size;
+                let v = (j as f32 + rng.gen::<f32>()) * cell_size;
+                offsets.push(((2.0 * u - 1.0) * radius, (2.0 * v - 1.0) * radius));
+            }
+        }
+
+        // Whatever doesn't fit in the grid is placed at random.
+        while offsets.len() < count as usize {
+            let u: f32 = rng.gen();
+            let v: f32 = rng.gen();
+            offsets.push(((2.0 * u - 1.0) * radius, (2.0 * v - 1.0) * radius));
+        }
+
+        offsets
+    }
+}
+
+/// The random numbers used to trace a pixel during a pass of a frame.
+///
+/// They only depend on the seed, the pass and the pixel, so the same frame comes out
+/// no matter the order in which pixels are traced or how many threads trace them.
+pub fn pixel_rng(seed: u64, pass: u32, pixel: usize) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(pixel as u64);
+    // Every pass gets its own stretch of 2^40 numbers of the stream of the pixel
+    rng.set_word_pos((pass as u128) << 40);
+    rng
+}
+
+/// Picks a random direction in the hemisphere around `normal`, directions closer to the
+/// normal are more likely following the cosine of the angle between them.
+pub fn cosine_sample_hemisphere<R: Rng + ?Sized>(normal: &Vec3, rng: &mut R) -> Vec3 {
+    let (u, v): (f32, f32) = (rng.gen(), rng.gen());
+    let radius = u.sqrt();
+    let theta = 2.0 * PI * v;
+    let x = radius * theta.cos();
+    let y = radius * theta.sin();
+    let z = (1.0 - u).max(0.0).sqrt();
+
+    // Any vector that isn't parallel to the normal works to build the basis
+    let helper = if normal.x.abs() > 0.9 {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let tangent = normal.cross(&helper).normalize();
+    let bitangent = normal.cross(&tangent);
+
+    (tangent * x + bitangent * y + normal * z).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_peak_at_the_center_and_vanish_at_the_radius() {
+        for filter in [
+            PixelFilter::Box,
+            PixelFilter::Tent,
+            PixelFilter::Gaussian,
+            PixelFilter::Mitchell,
+        ] {
+            let radius = filter.radius();
+            let center = filter.weight(0.0, 0.0);
+
+            assert!(center > 0.0, "{filter:?}");
+            assert!(filter.weight(0.25, 0.1) <= center, "{filter:?}");
+            assert!(filter.weight(radius + 0.01, 0.0).abs() < 1e-6, "{filter:?}");
+        }
+    }
+
+    #[test]
+    fn test_stratified_offsets_fill_every_cell() {
+        let mut rng = rand::thread_rng();
+        let offsets = SamplePattern::Stratified.offsets(16, 0.5, &mut rng);
+
+        assert_eq!(offsets.len(), 16);
+        let mut cells = vec![false; 16];
+        for (dx, dy) in offsets {
+            assert!((-0.5..=0.5).contains(&dx) && (-0.5..=0.5).contains(&dy));
+            let i = (((dx + 0.5) * 4.0) as usize).min(3);
+            let j = (((dy + 0.5) * 4.0) as usize).min(3);
+            cells[i * 4 + j] = true;
+        }
+        assert!(cells.into_iter().all(|c| c));
+    }
+
+    #[test]
+    fn test_pixel_numbers_only_depend_on_seed_pass_and_pixel() {
+        let numbers = |seed, pass, pixel| -> Vec<u32> {
+            let mut rng = pixel_rng(seed, pass, pixel);
+            (0..8).map(|_| rng.gen()).collect()
+        };
+
+        assert_eq!(numbers(3, 1, 42), numbers(3, 1, 42));
+        assert_ne!(numbers(3, 1, 42), numbers(4, 1, 42));
+        assert_ne!(numbers(3, 1, 42), numbers(3, 2, 42));
+        assert_ne!(numbers(3, 1, 42), numbers(3, 1, 43));
+    }
+
+    #[test]
+    fn test_cosine_samples_stay_in_the_hemisphere() {
+        let mut rng = rand::thread_rng();
+        let normal = Vec3::new(1.0, 2.0, -0.5).normalize();
+
+        let mut average_cosine = 0.0;
+        for _ in 0..1000 {
+            let direction = cosine_sample_hemisphere(&normal, &mut rng);
+            assert!((direction.magnitude() - 1.0).abs() < 1e-4);
+            assert!(direction.dot(&normal) >= 0.0);
+            average_cosine += direction.dot(&normal) / 1000.0;
+        }
+
+        // The expected value of the cosine with this distribution is 2/3
+        assert!((average_cosine - 2.0 / 3.0).abs() < 0.05);
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::OnceLock;
+
+use glm::Vec3;
+
+use crate::{
+    bvh::Bvh,
+    cube::BoxBounds,
+    raytracer::{Intersect, Traceable},
+};
+
+/// Holds every object of the world regardless of its shape.
+///
+/// Rays are traced against the whole collection at once, so every primitive
+/// occludes, shadows, reflects and refracts every other one.
+///
+/// The objects are organized in a `Bvh` that gets built the first time a ray is traced
+/// and is thrown away every time the scene is modified.
+#[derive(Debug, Default)]
+pub struct Scene {
+    objects: Vec<Box<dyn Traceable>>,
+    bvh: OnceLock<Bvh>,
+}
+
+impl Scene {
+    /// Creates a new Scene with the given objects.
+    pub fn new(objects: Vec<Box<dyn Traceable>>) -> Self {
+        Scene {
+            objects,
+            bvh: OnceLock::new(),
+        }
+    }
+
+    /// Adds a single object to the scene.
+    pub fn push(&mut self, object: impl Traceable + 'static) {
+        self.bvh = OnceLock::new();
+        self.objects.push(Box::new(object));
+    }
+
+    /// Adds all the objects of the iterator to the scene.
+    pub fn extend<T: Traceable + 'static>(&mut self, objects: impl IntoIterator<Item = T>) {
+        self.bvh = OnceLock::new();
+        self.objects.extend(
+            objects
+                .into_iter()
+                .map(|object| Box::new(object) as Box<dyn Traceable>),
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| Bvh::new(&self.objects))
+    }
+}
+
+impl Traceable for Scene {
+    /// Finds the closest object the ray hits.
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Option<Intersect> {
+        self.bvh()
+            .ray_intersect(&self.objects, ray_origin, ray_direction)
+    }
+
+    fn bounding_box(&self) -> BoxBounds {
+        self.bvh().bounds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cube::Cube, material::RUBBER, sphere::Sphere, texture::CubeFace};
+
+    #[test]
+    fn test_closest_object_wins_regardless_of_type() {
+        let mut scene = Scene::default();
+        scene.push(Sphere {
+            id: 0,
+            center: Vec3::new(0.0, 0.0, -10.0),
+            radius: 1.0,
+            material: RUBBER,
+        });
+        scene.push(Cube::new(
+            1,
+            Vec3::new(0.0, 0.0, -5.0),
+            1.0,
+            RUBBER,
+            Vec3::new(0.0, 1.0, 0.0),
+        ));
+
+        let intersect = scene
+            .ray_intersect(&Vec3::zeros(), &Vec3::new(0.0, 0.0, -1.0))
+            .unwrap();
+
+        assert!(matches!(intersect.face, CubeFace::FORWARDS));
+        assert!((intersect.distance - 4.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_objects_behind_the_ray_are_ignored() {
+        let mut scene = Scene::default();
+        scene.push(Cube::new(
+            0,
+            Vec3::new(0.0, 0.0, 5.0),
+            1.0,
+            RUBBER,
+            Vec3::new(0.0, 1.0, 0.0),
+        ));
+
+        let intersect = scene.ray_intersect(&Vec3::zeros(), &Vec3::new(0.0, 0.0, -1.0));
+
+        assert!(intersect.is_none());
+    }
+}
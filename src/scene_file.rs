@@ -0,0 +1,898 @@
+//! Loads a `Model` from a JSON scene description.
+//!
+//! A scene file looks like this (every field not marked as required is optional):
+//!
+//! ```json
+//! {
+//!     "camera": { "eye": [0, 0, 10], "center": [0, 0, 0], "up": [0, 1, 0] },
+//!     "time_of_day": "day",
+//!     "sky_color": "#87CEEB",
+//!     "ambient_light": 0.15,
+//!     "materials": {
+//!         "shiny_gold": { "base": "gold", "reflectivity": 0.4 }
+//!     },
+//!     "lights": [
+//!         { "position": [0, 3, -3.5], "color": "#361B6F", "intensity": 0.25 }
+//!     ],
+//!     "objects": [
+//!         { "type": "cube", "center": [0, 0, 0], "size": 1.5, "material": "obsidian" },
+//!         { "type": "sphere", "center": [2, 1, 0], "radius": 0.5, "material": "shiny_gold" },
+//!         {
+//!             "type": "voxel_grid",
+//!             "origin": [-6, -2.25, -6],
+//!             "dimensions": [8, 1, 8],
+//!             "block_size": 1.5,
+//!             "blocks": [
+//!                 { "from": [0, 0, 0], "to": [7, 0, 7], "material": "netherrack" },
+//!                 { "from": [3, 0, 4], "material": "magma" }
+//!             ]
+//!         },
+//!         { "type": "mesh", "file": "models/pyramid.obj", "position": [2, -1.5, 2], "scale": 0.5 },
+//!         { "type": "vox", "file": "models/tower.vox", "origin": [-8, -1.5, -8], "block_size": 0.5 },
+//!         {
+//!             "type": "schematic",
+//!             "file": "models/shrine.schem",
+//!             "origin": [4, -1.5, -8],
+//!             "blocks": { "minecraft:glowstone": "magma" },
+//!             "fallback": "rubber"
+//!         },
+//!         { "type": "terrain", "origin": [-24, -12, -24], "size": [48, 48], "seed": 42 }
+//!     ]
+//! }
+//! ```
+//!
+//! * `camera` (required): Where the camera is, where it looks at and its up vector (defaults to `[0, 1, 0]`).
+//! * `time_of_day`: Either `"day"` (default) or `"night"`, it sets the sky color and the light from above.
+//! * `sky_color`: Overrides the sky color given by `time_of_day`.
+//! * `ambient_light`: The amount of light every surface receives regardless of the lights (defaults to 0.15).
+//! * `materials`: Custom materials by name. Each one may start from a predefined one with `base`
+//!   and override any of `diffuse`, `texture`, `specular`, `albedo`, `reflectivity`,
+//!   `transparency`, `refractive_index` and `emission`.
+//! * `lights`: Point lights with a `position`, `color` and `intensity`.
+//! * `objects` (required): Every object in the scene, the `type` is one of `cube`, `sphere`, `voxel_grid`, `mesh`, `vox`, `schematic`, `region`, `terrain` or `heightmap`.
+//!   The `size` of a cube is either a number or the length of its sides as `[x, y, z]`, it can be
+//!   rotated with `up` (where its top face looks at) and `forward` (where its front face looks at).
+//!   Blocks inside a voxel grid fill every cell from `from` to `to` (both inclusive), `to` defaults to `from`.
+//!   Voxel grids need at least one cell along every axis and their blocks must be inside them.
+//!   A mesh loads the Wavefront OBJ `file` (relative to the scene file) with its own MTL materials,
+//!   moved to `position` and multiplied by `scale`.
+//!   A vox object loads the MagicaVoxel `file` (relative to the scene file) as a voxel grid with
+//!   blocks of `block_size` (defaults to 1) starting at `origin`.
+//!   A schematic loads a Minecraft structure (`.nbt`) or Sponge schematic (`.schem`) the same way,
+//!   `blocks` picks the material of specific blocks and `fallback` the one of unknown blocks
+//!   (defaults to `"stone"`), with `skip_unknown` they are left out instead.
+//!   A region reads the blocks from `from` to `to` (world coordinates, both inclusive) out of the
+//!   `directory` with the region files of a saved world, with the same `blocks`, `fallback` and
+//!   `skip_unknown` as a schematic. `origin` defaults to `from` so the blocks keep their coordinates.
+//!   A terrain generates hills out of noise starting at `origin`, with a `size` of `[x, z]` blocks,
+//!   up to `height` blocks tall, made of `strata` (`{ "thickness": 3, "material": "dirt" }` from the
+//!   surface down) over a `bedrock` and flooded with water up to `sea_level`.
+//!   The `seed`, `noise` (`"perlin"` or `"simplex"`), `octaves`, `frequency`, `base_height`,
+//!   `roughness`, `cave_threshold` and `cave_frequency` shape it (see `TerrainSettings`).
+//!   A heightmap turns every pixel of the grayscale image `file` into a column of blocks up to
+//!   `height` blocks tall (white) starting at `origin`. The top `surface_depth` blocks of a column
+//!   take the material of the first of the `bands` (`{ "max_height": 0.5, "max_slope": 2, "material": "moss" }`,
+//!   the height as a fraction of `height`) that matches it and the rest are `ground`.
+//!   With a `color_map` image the columns take instead the material of the closest of the
+//!   `colors` (`{ "color": "#FFD700", "material": "gold" }`). `sea_level` floods it like a terrain.
+//!
+//! Materials are referenced either by name (a custom one, one of the predefined ones like
+//! `"stone"`, `"portal"` or `"rubber"`, or the name of a texture of the registry for a block
+//! covered by it) or written inline with the same fields as the custom ones.
+//! Imported Minecraft blocks named like a texture of the registry are covered by it too.
+//! Colors are either `"#RRGGBB"` strings or `[r, g, b]` arrays.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use glm::Vec3;
+use serde::Deserialize;
+
+use crate::{
+    anvil::{load_region, AnvilError, BlockBox},
+    camera::Camera,
+    color::Color,
+    cube::Cube,
+    gltf_scene::{load_gltf, GltfError},
+    heightmap::{load_heightmap, HeightmapError, HeightmapSettings, MaterialBand},
+    light::Light,
+    material::Material,
+    minecraft::BlockMaterials,
+    obj::{load_obj, MeshTransform, ObjError},
+    scene::Scene,
+    schematic::{load_schematic, SchematicError},
+    sphere::Sphere,
+    terrain::{generate_terrain, NoiseKind, Stratum, TerrainSettings},
+    texture::TextureRegistry,
+    vox::{load_vox, VoxError},
+    voxel::VoxelGrid,
+    Model, TimeOfDay,
+};
+
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(PathBuf, std::io::Error),
+    Parse(serde_json::Error),
+    UnknownMaterial(String),
+    UnknownTexture(String),
+    InvalidColor(String),
+    /// A direction of an object, named by its field, that has no length.
+    ZeroDirection(&'static str),
+    /// A voxel grid with no cells along some axis.
+    EmptyVoxelGrid([usize; 3]),
+    /// Blocks from a cell to another one that go past the dimensions of their grid.
+    BlockOutOfGrid([usize; 3], [usize; 3], [usize; 3]),
+    Mesh(ObjError),
+    Gltf(GltfError),
+    Vox(VoxError),
+    Schematic(SchematicError),
+    Region(AnvilError),
+    Heightmap(HeightmapError),
+}
+
+impl Display for SceneFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneFileError::Io(path, error) => {
+                write!(f, "Couldn't read scene file {}: {error}", path.display())
+            }
+            SceneFileError::Parse(error) => write!(f, "Invalid scene file: {error}"),
+            SceneFileError::UnknownMaterial(name) => write!(f, "Unknown material `{name}`"),
+            SceneFileError::UnknownTexture(name) => write!(f, "Unknown texture `{name}`"),
+            SceneFileError::InvalidColor(value) => write!(f, "Invalid color `{value}`"),
+            SceneFileError::ZeroDirection(field) => {
+                write!(f, "The `{field}` direction can't be [0, 0, 0]")
+            }
+            SceneFileError::EmptyVoxelGrid(dimensions) => {
+                write!(
+                    f,
+                    "Voxel grid with no cells, its dimensions are {dimensions:?}"
+                )
+            }
+            SceneFileError::BlockOutOfGrid(from, to, dimensions) => write!(
+                f,
+                "Blocks from {from:?} to {to:?} go past the voxel grid of dimensions {dimensions:?}"
+            ),
+            SceneFileError::Mesh(error) => write!(f, "{error}"),
+            SceneFileError::Gltf(error) => write!(f, "{error}"),
+            SceneFileError::Vox(error) => write!(f, "{error}"),
+            SceneFileError::Schematic(error) => write!(f, "{error}"),
+            SceneFileError::Region(error) => write!(f, "{error}"),
+            SceneFileError::Heightmap(error) => write!(f, "{error}"),
+        }
+    }
+}
+impl std::error::Error for SceneFileError {}
+
+type Vec3Def = [f32; 3];
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorDef {
+    Hex(String),
+    Rgb([u8; 3]),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDef {
+    eye: Vec3Def,
+    center: Vec3Def,
+    #[serde(default = "default_up")]
+    up: Vec3Def,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+enum TimeOfDayDef {
+    #[default]
+    Day,
+    Night,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum NoiseDef {
+    Perlin,
+    Simplex,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StratumDef {
+    thickness: usize,
+    material: MaterialDef,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BandDef {
+    max_height: Option<f32>,
+    max_slope: Option<usize>,
+    material: MaterialDef,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorMaterialDef {
+    color: ColorDef,
+    material: MaterialDef,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightDef {
+    position: Vec3Def,
+    color: ColorDef,
+    intensity: f32,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct MaterialFields {
+    base: Option<String>,
+    diffuse: Option<ColorDef>,
+    texture: Option<String>,
+    specular: Option<f32>,
+    albedo: Option<[f32; 2]>,
+    reflectivity: Option<f32>,
+    transparency: Option<f32>,
+    refractive_index: Option<f32>,
+    emission: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SizeDef {
+    Uniform(f32),
+    Extents(Vec3Def),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaterialDef {
+    Named(String),
+    Inline(MaterialFields),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlockDef {
+    from: [usize; 3],
+    to: Option<[usize; 3]>,
+    material: MaterialDef,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDef {
+    Cube {
+        center: Vec3Def,
+        size: SizeDef,
+        material: MaterialDef,
+        #[serde(default = "default_up")]
+        up: Vec3Def,
+        #[serde(default = "default_forward")]
+        forward: Vec3Def,
+    },
+    Sphere {
+        center: Vec3Def,
+        radius: f32,
+        material: MaterialDef,
+    },
+    VoxelGrid {
+        origin: Vec3Def,
+        dimensions: [usize; 3],
+        block_size: f32,
+        blocks: Vec<BlockDef>,
+    },
+    Mesh {
+        file: PathBuf,
+        #[serde(default)]
+        position: Vec3Def,
+        #[serde(default = "default_scale")]
+        scale: f32,
+    },
+    Vox {
+        file: PathBuf,
+        #[serde(default)]
+        origin: Vec3Def,
+        #[serde(default = "default_scale")]
+        block_size: f32,
+    },
+    Schematic {
+        file: PathBuf,
+        #[serde(default)]
+        origin: Vec3Def,
+        #[serde(default = "default_scale")]
+        block_size: f32,
+        /// Materials of specific blocks by name, e.g. `"minecraft:glowstone": "magma"`.
+        #[serde(default)]
+        blocks: HashMap<String, MaterialDef>,
+        fallback: Option<MaterialDef>,
+        #[serde(default)]
+        skip_unknown: bool,
+    },
+    Region {
+        /// The `region` directory of a saved world.
+        directory: PathBuf,
+        from: [i32; 3],
+        to: [i32; 3],
+        origin: Option<Vec3Def>,
+        #[serde(default = "default_scale")]
+        block_size: f32,
+        #[serde(default)]
+        blocks: HashMap<String, MaterialDef>,
+        fallback: Option<MaterialDef>,
+        #[serde(default)]
+        skip_unknown: bool,
+    },
+    /// Every field but the position falls back to `TerrainSettings::default()`.
+    Terrain {
+        #[serde(default)]
+        origin: Vec3Def,
+        #[serde(default = "default_scale")]
+        block_size: f32,
+        size: Option<[usize; 2]>,
+        height: Option<usize>,
+        seed: Option<u32>,
+        noise: Option<NoiseDef>,
+        octaves: Option<usize>,
+        frequency: Option<f64>,
+        base_height: Option<f64>,
+        roughness: Option<f64>,
+        sea_level: Option<usize>,
+        strata: Option<Vec<StratumDef>>,
+        bedrock: Option<MaterialDef>,
+        cave_threshold: Option<f64>,
+        cave_frequency: Option<f64>,
+    },
+    /// Every field but the images and the position falls back to `HeightmapSettings::default()`.
+    Heightmap {
+        file: PathBuf,
+        color_map: Option<PathBuf>,
+        #[serde(default)]
+        origin: Vec3Def,
+        #[serde(default = "default_scale")]
+        block_size: f32,
+        height: Option<usize>,
+        bands: Option<Vec<BandDef>>,
+        surface_depth: Option<usize>,
+        ground: Option<MaterialDef>,
+        sea_level: Option<usize>,
+        #[serde(default)]
+        colors: Vec<ColorMaterialDef>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    camera: CameraDef,
+    #[serde(default)]
+    time_of_day: TimeOfDayDef,
+    sky_color: Option<ColorDef>,
+    #[serde(default = "default_ambient_light")]
+    ambient_light: f32,
+    #[serde(default)]
+    materials: HashMap<String, MaterialFields>,
+    #[serde(default)]
+    lights: Vec<LightDef>,
+    objects: Vec<ObjectDef>,
+}
+
+fn default_up() -> Vec3Def {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_forward() -> Vec3Def {
+    [0.0, 0.0, 1.0]
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+pub(crate) fn default_ambient_light() -> f32 {
+    0.15
+}
+
+fn to_vec3(value: &Vec3Def) -> Vec3 {
+    Vec3::new(value[0], value[1], value[2])
+}
+
+fn to_color(value: &ColorDef) -> Result<Color, SceneFileError> {
+    match value {
+        ColorDef::Rgb([r, g, b]) => Ok(Color::new(*r, *g, *b)),
+        ColorDef::Hex(hex) => hex
+            .strip_prefix('#')
+            .filter(|digits| digits.len() == 6)
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .map(Color::from)
+            .ok_or_else(|| SceneFileError::InvalidColor(hex.clone())),
+    }
+}
+
+/// Resolves materials by name, custom ones take precedence over the predefined ones
+/// and those over the blocks made out of a texture of the registry.
+struct MaterialResolver<'a> {
+    custom: &'a HashMap<String, MaterialFields>,
+    textures: &'a TextureRegistry,
+}
+
+impl MaterialResolver<'_> {
+    fn resolve(&self, material: &MaterialDef) -> Result<Material, SceneFileError> {
+        match material {
+            MaterialDef::Named(name) => match self.custom.get(name) {
+                Some(fields) => self.build_material(fields),
+                None => Material::from_name(name)
+                    .or_else(|| self.textures.id(name).map(Material::from_texture))
+                    .ok_or_else(|| SceneFileError::UnknownMaterial(name.clone())),
+            },
+            MaterialDef::Inline(fields) => self.build_material(fields),
+        }
+    }
+
+    /// Decides the materials of imported Minecraft blocks.
+    fn block_materials(
+        &self,
+        blocks: &HashMap<String, MaterialDef>,
+        fallback: Option<&MaterialDef>,
+        skip_unknown: bool,
+    ) -> Result<BlockMaterials, SceneFileError> {
+        let mut block_materials = BlockMaterials::default();
+        block_materials.use_textures(self.textures);
+        if let Some(fallback) = fallback {
+            block_materials.fallback = Some(self.resolve(fallback)?);
+        }
+        if skip_unknown {
+            block_materials.fallback = None;
+        }
+        for (block, material) in blocks {
+            block_materials.set(block, Some(self.resolve(material)?));
+        }
+
+        Ok(block_materials)
+    }
+
+    fn build_material(&self, fields: &MaterialFields) -> Result<Material, SceneFileError> {
+        let mut material = match &fields.base {
+            Some(base) => Material::from_name(base)
+                .ok_or_else(|| SceneFileError::UnknownMaterial(base.clone()))?,
+            None => Material::default(),
+        };
+
+        if let Some(diffuse) = &fields.diffuse {
+            material.diffuse = to_color(diffuse)?;
+        }
+        if let Some(texture) = &fields.texture {
+            material.texture = Some(
+                self.textures
+                    .id(texture)
+                    .ok_or_else(|| SceneFileError::UnknownTexture(texture.clone()))?,
+            );
+        }
+        if let Some(specular) = fields.specular {
+            material.specular = specular;
+        }
+        if let Some([absorbed, reflected]) = fields.albedo {
+            material.albedo = (absorbed, reflected);
+        }
+        if let Some(reflectivity) = fields.reflectivity {
+            material.reflectivity = reflectivity;
+        }
+        if let Some(transparency) = fields.transparency {
+            material.transparency = transparency;
+        }
+        if let Some(refractive_index) = fields.refractive_index {
+            material.refractive_index = refractive_index;
+        }
+        if let Some(emission) = fields.emission {
+            material.emission = emission;
+        }
+
+        Ok(material)
+    }
+}
+
+/// Reads the scene file located at `file_path` and builds a `Model` out of it.
+///
+/// Files ending in `.gltf` or `.glb` are imported as glTF scenes instead, see `load_gltf`.
+pub fn load_model(
+    file_path: impl AsRef<Path>,
+    textures: TextureRegistry,
+) -> Result<Model, SceneFileError> {
+    let file_path = file_path.as_ref();
+    let extension = file_path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    if let Some("gltf" | "glb") = extension.as_deref() {
+        return load_gltf(file_path, textures).map_err(SceneFileError::Gltf);
+    }
+
+    let source = fs::read_to_string(file_path)
+        .map_err(|e| SceneFileError::Io(file_path.to_path_buf(), e))?;
+
+    let base_dir = file_path.parent().unwrap_or(Path::new(""));
+    parse_model_in(&source, base_dir, textures)
+}
+
+/// Builds a `Model` out of the contents of a scene file.
+/// Files referenced by the scene are looked for relative to the current directory.
+pub fn parse_model(source: &str, textures: TextureRegistry) -> Result<Model, SceneFileError> {
+    parse_model_in(source, Path::new(""), textures)
+}
+
+/// Builds a `Model` out of the contents of a scene file whose relative paths start at `base_dir`.
+fn parse_model_in(
+    source: &str,
+    base_dir: &Path,
+    textures: TextureRegistry,
+) -> Result<Model, SceneFileError> {
+    let file: SceneFile = serde_json::from_str(source).map_err(SceneFileError::Parse)?;
+    let materials = MaterialResolver {
+        custom: &file.materials,
+        textures: &textures,
+    };
+
+    let mut scene = Scene::default();
+    for (id, object) in file.objects.iter().enumerate() {
+        let id = id as u32;
+        match object {
+            ObjectDef::Cube {
+                center,
+                size,
+                material,
+                up,
+                forward,
+            } => {
+                let extents = match size {
+                    SizeDef::Uniform(size) => Vec3::repeat(*size),
+                    SizeDef::Extents(extents) => to_vec3(extents),
+                };
+                for (field, direction) in [("up", up), ("forward", forward)] {
+                    if to_vec3(direction).magnitude() == 0.0 {
+                        return Err(SceneFileError::ZeroDirection(field));
+                    }
+                }
+                scene.push(Cube::oriented(
+                    id,
+                    to_vec3(center),
+                    extents,
+                    materials.resolve(material)?,
+                    to_vec3(up),
+                    to_vec3(forward),
+                ))
+            }
+            ObjectDef::Sphere {
+                center,
+                radius,
+                material,
+            } => scene.push(Sphere {
+                id,
+                center: to_vec3(center),
+                radius: *radius,
+                material: materials.resolve(material)?,
+            }),
+            ObjectDef::VoxelGrid {
+                origin,
+                dimensions,
+                block_size,
+                blocks,
+            } => {
+                if dimensions.contains(&0) {
+                    return Err(SceneFileError::EmptyVoxelGrid(*dimensions));
+                }
+                let mut grid = VoxelGrid::new(to_vec3(origin), *dimensions, *block_size);
+                for block in blocks {
+                    let to = block.to.unwrap_or(block.from);
+                    if (0..3).any(|i| block.from[i].max(to[i]) >= dimensions[i]) {
+                        return Err(SceneFileError::BlockOutOfGrid(block.from, to, *dimensions));
+                    }
+                    let block_id = grid.register_material(materials.resolve(&block.material)?);
+                    for z in block.from[2]..=to[2] {
+                        for y in block.from[1]..=to[1] {
+                            for x in block.from[0]..=to[0] {
+                                grid.set_block_id(x, y, z, Some(block_id));
+                            }
+                        }
+                    }
+                }
+                scene.push(grid);
+            }
+            ObjectDef::Mesh {
+                file,
+                position,
+                scale,
+            } => {
+                let transform = MeshTransform {
+                    position: to_vec3(position),
+                    scale: *scale,
+                };
+                let mesh = load_obj(base_dir.join(file), transform, &textures)
+                    .map_err(SceneFileError::Mesh)?;
+                scene.push(mesh);
+            }
+            ObjectDef::Vox {
+                file,
+                origin,
+                block_size,
+            } => {
+                let grid = load_vox(base_dir.join(file), to_vec3(origin), *block_size)
+                    .map_err(SceneFileError::Vox)?;
+                scene.push(grid);
+            }
+            ObjectDef::Schematic {
+                file,
+                origin,
+                block_size,
+                blocks,
+                fallback,
+                skip_unknown,
+            } => {
+                let block_materials =
+                    materials.block_materials(blocks, fallback.as_ref(), *skip_unknown)?;
+                let grid = load_schematic(
+                    base_dir.join(file),
+                    to_vec3(origin),
+                    *block_size,
+                    &block_materials,
+                )
+                .map_err(SceneFileError::Schematic)?;
+                scene.push(grid);
+            }
+            ObjectDef::Region {
+                directory,
+                from,
+                to,
+                origin,
+                block_size,
+                blocks,
+                fallback,
+                skip_unknown,
+            } => {
+                let bounds = BlockBox::new(*from, *to);
+                let origin = origin.unwrap_or_else(|| bounds.min.map(|c| c as f32));
+                let block_materials =
+                    materials.block_materials(blocks, fallback.as_ref(), *skip_unknown)?;
+                let grid = load_region(
+                    base_dir.join(directory),
+                    &bounds,
+                    to_vec3(&origin),
+                    *block_size,
+                    &block_materials,
+                )
+                .map_err(SceneFileError::Region)?;
+                scene.push(grid);
+            }
+            ObjectDef::Terrain {
+                origin,
+                block_size,
+                size,
+                height,
+                seed,
+                noise,
+                octaves,
+                frequency,
+                base_height,
+                roughness,
+                sea_level,
+                strata,
+                bedrock,
+                cave_threshold,
+                cave_frequency,
+            } => {
+                let defaults = TerrainSettings::default();
+                let strata = match strata {
+                    Some(strata) => strata
+                        .iter()
+                        .map(|stratum| {
+                            Ok(Stratum {
+                                thickness: stratum.thickness,
+                                material: materials.resolve(&stratum.material)?,
+                            })
+                        })
+                        .collect::<Result<Vec<Stratum>, SceneFileError>>()?,
+                    None => defaults.strata,
+                };
+                let bedrock = match bedrock {
+                    Some(bedrock) => Some(materials.resolve(bedrock)?),
+                    None => defaults.bedrock,
+                };
+                let noise = match noise {
+                    Some(NoiseDef::Perlin) => NoiseKind::Perlin,
+                    Some(NoiseDef::Simplex) => NoiseKind::Simplex,
+                    None => defaults.noise,
+                };
+                let settings = TerrainSettings {
+                    size: size.unwrap_or(defaults.size),
+                    height: height.unwrap_or(defaults.height),
+                    seed: seed.unwrap_or(defaults.seed),
+                    noise,
+                    octaves: octaves.unwrap_or(defaults.octaves),
+                    frequency: frequency.unwrap_or(defaults.frequency),
+                    base_height: base_height.unwrap_or(defaults.base_height),
+                    roughness: roughness.unwrap_or(defaults.roughness),
+                    sea_level: sea_level.unwrap_or(defaults.sea_level),
+                    strata,
+                    bedrock,
+                    cave_threshold: cave_threshold.unwrap_or(defaults.cave_threshold),
+                    cave_frequency: cave_frequency.unwrap_or(defaults.cave_frequency),
+                };
+                scene.push(generate_terrain(to_vec3(origin), *block_size, &settings));
+            }
+            ObjectDef::Heightmap {
+                file,
+                color_map,
+                origin,
+                block_size,
+                height,
+                bands,
+                surface_depth,
+                ground,
+                sea_level,
+                colors,
+            } => {
+                let defaults = HeightmapSettings::default();
+                let bands = match bands {
+                    Some(bands) => bands
+                        .iter()
+                        .map(|band| {
+                            Ok(MaterialBand {
+                                max_height: band.max_height,
+                                max_slope: band.max_slope,
+                                material: materials.resolve(&band.material)?,
+                            })
+                        })
+                        .collect::<Result<Vec<MaterialBand>, SceneFileError>>()?,
+                    None => defaults.bands,
+                };
+                let ground = match ground {
+                    Some(ground) => materials.resolve(ground)?,
+                    None => defaults.ground,
+                };
+                let colors = colors
+                    .iter()
+                    .map(|entry| Ok((to_color(&entry.color)?, materials.resolve(&entry.material)?)))
+                    .collect::<Result<Vec<(Color, Material)>, SceneFileError>>()?;
+                let settings = HeightmapSettings {
+                    height: height.unwrap_or(defaults.height),
+                    bands,
+                    surface_depth: surface_depth.unwrap_or(defaults.surface_depth),
+                    ground,
+                    sea_level: sea_level.unwrap_or(defaults.sea_level),
+                    colors,
+                };
+                let color_map = color_map.as_ref().map(|color_map| base_dir.join(color_map));
+                let grid = load_heightmap(
+                    base_dir.join(file),
+                    color_map.as_deref(),
+                    to_vec3(origin),
+                    *block_size,
+                    &settings,
+                )
+                .map_err(SceneFileError::Heightmap)?;
+                scene.push(grid);
+            }
+        }
+    }
+
+    let lights = file
+        .lights
+        .iter()
+        .map(|light| {
+            Ok(Light {
+                position: to_vec3(&light.position),
+                color: to_color(&light.color)?,
+                intensity: light.intensity,
+            })
+        })
+        .collect::<Result<Vec<Light>, SceneFileError>>()?;
+
+    let daytime = match file.time_of_day {
+        TimeOfDayDef::Day => TimeOfDay::Day,
+        TimeOfDayDef::Night => TimeOfDay::Night,
+    };
+    let sky_color = match &file.sky_color {
+        Some(color) => to_color(color)?,
+        None => daytime.sky_color(),
+    };
+
+    let camera = Camera::new(
+        to_vec3(&file.camera.eye),
+        to_vec3(&file.camera.center),
+        to_vec3(&file.camera.up),
+    );
+
+    Ok(Model {
+        scene,
+        lights,
+        ambient_light: file.ambient_light,
+        camera,
+        textures,
+        top_light: daytime.top_light(),
+        daytime,
+        sky_color,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::GOLD;
+
+    #[test]
+    fn test_parse_colors() {
+        let hex = to_color(&ColorDef::Hex("#361B6F".to_string())).unwrap();
+        let rgb = to_color(&ColorDef::Rgb([0x36, 0x1B, 0x6F])).unwrap();
+
+        assert_eq!(hex, rgb);
+        assert!(to_color(&ColorDef::Hex("361B6F".to_string())).is_err());
+        assert!(to_color(&ColorDef::Hex("#361B6".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_custom_materials_override_their_base() {
+        let custom: HashMap<String, MaterialFields> =
+            serde_json::from_str(r#"{ "shiny_gold": { "base": "gold", "reflectivity": 0.4 } }"#)
+                .unwrap();
+        let textures = TextureRegistry::default();
+        let materials = MaterialResolver {
+            custom: &custom,
+            textures: &textures,
+        };
+
+        let shiny_gold = materials
+            .resolve(&MaterialDef::Named("shiny_gold".to_string()))
+            .unwrap();
+        let gold = materials
+            .resolve(&MaterialDef::Named("Gold".to_string()))
+            .unwrap();
+
+        assert_eq!(shiny_gold.reflectivity, 0.4);
+        assert_eq!(shiny_gold.texture, GOLD.texture);
+        assert_eq!(gold, GOLD);
+        assert!(materials
+            .resolve(&MaterialDef::Named("bedrock".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_cubes_reject_zero_directions() {
+        let source = r#"{ "camera": { "eye": [0, 0, 5], "center": [0, 0, 0] },
+            "objects": [{ "type": "cube", "center": [0, 0, 0], "size": 1.0,
+                          "material": "stone", "up": [0, 0, 0] }] }"#;
+
+        assert!(matches!(
+            parse_model(source, TextureRegistry::default()),
+            Err(SceneFileError::ZeroDirection("up"))
+        ));
+    }
+
+    #[test]
+    fn test_voxel_grids_reject_empty_dimensions_and_stray_blocks() {
+        let scene = |dimensions: &str, to: &str| {
+            let source = format!(
+                r#"{{ "camera": {{ "eye": [0, 0, 5], "center": [0, 0, 0] }},
+                    "objects": [{{ "type": "voxel_grid", "origin": [0, 0, 0],
+                    "dimensions": {dimensions}, "block_size": 1.0,
+                    "blocks": [{{ "from": [0, 0, 0], "to": {to}, "material": "stone" }}] }}] }}"#
+            );
+            parse_model(&source, TextureRegistry::default())
+        };
+
+        assert!(scene("[2, 2, 2]", "[1, 1, 1]").is_ok());
+        assert!(matches!(
+            scene("[0, 4, 4]", "[0, 0, 0]"),
+            Err(SceneFileError::EmptyVoxelGrid([0, 4, 4]))
+        ));
+        assert!(matches!(
+            scene("[2, 2, 2]", "[100000, 100000, 100000]"),
+            Err(SceneFileError::BlockOutOfGrid(..))
+        ));
+    }
+}
@@ -0,0 +1,404 @@
+//! Loads Minecraft builds saved as structure block files (`.nbt`) or Sponge schematics
+//! (`.schem`, versions 1 to 3) as a `VoxelGrid`.
+//!
+//! Both kinds of files are gzip compressed NBT and are told apart by their contents.
+//! Minecraft is Y up like the rest of the scene, so blocks keep their coordinates.
+
+use std::{
+    fmt::Display,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use glm::Vec3;
+
+use crate::{
+    minecraft::BlockMaterials,
+    nbt::{read_gzip_nbt, NbtError, NbtTag},
+    voxel::VoxelGrid,
+};
+
+#[derive(Debug)]
+pub enum SchematicError {
+    Io(PathBuf, std::io::Error),
+    Nbt(PathBuf, NbtError),
+    Invalid(PathBuf, String),
+}
+
+impl Display for SchematicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchematicError::Io(path, error) => {
+                write!(f, "Couldn't read schematic {}: {error}", path.display())
+            }
+            SchematicError::Nbt(path, error) => {
+                write!(f, "Invalid schematic {}: {error}", path.display())
+            }
+            SchematicError::Invalid(path, reason) => {
+                write!(f, "Invalid schematic {}: {reason}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchematicError {}
+
+/// The blocks of a build, by their index in a palette of block states.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+    /// Size of the build in the x, y and z axis.
+    pub size: [usize; 3],
+    /// Block states like `minecraft:oak_stairs[facing=north]`.
+    pub palette: Vec<String>,
+    /// The position and palette index of every block.
+    pub blocks: Vec<([usize; 3], usize)>,
+}
+
+fn get<'a>(tag: &'a NbtTag, key: &str) -> Result<&'a NbtTag, String> {
+    tag.get(key).ok_or_else(|| format!("missing `{key}`"))
+}
+
+fn get_usize(tag: &NbtTag, key: &str) -> Result<usize, String> {
+    let value = get(tag, key)?;
+    match value {
+        // Sizes of schematics are unsigned shorts
+        NbtTag::Short(short) => Ok(*short as u16 as usize),
+        _ => value
+            .as_i64()
+            .and_then(|value| usize::try_from(value).ok())
+            .ok_or_else(|| format!("`{key}` isn't a valid size")),
+    }
+}
+
+fn read_position(tag: &NbtTag, key: &str) -> Result<[usize; 3], String> {
+    let values = get(tag, key)?
+        .as_list()
+        .ok_or_else(|| format!("`{key}` isn't a list"))?;
+    let coordinates: Vec<usize> = values
+        .iter()
+        .map(|value| value.as_i64().and_then(|v| usize::try_from(v).ok()))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("`{key}` has invalid coordinates"))?;
+
+    match coordinates[..] {
+        [x, y, z] => Ok([x, y, z]),
+        _ => Err(format!("`{key}` doesn't have 3 coordinates")),
+    }
+}
+
+/// Makes sure a grid of `size` blocks isn't too big to be built.
+fn check_size(size: [usize; 3]) -> Result<(), String> {
+    match VoxelGrid::cell_count(size) {
+        Some(_) => Ok(()),
+        None => Err(format!(
+            "its size {size:?} has more than {} blocks",
+            VoxelGrid::MAX_CELLS
+        )),
+    }
+}
+
+/// Reads a vanilla structure, saved by a structure block.
+fn parse_structure(root: &NbtTag) -> Result<Schematic, String> {
+    let size = read_position(root, "size")?;
+    check_size(size)?;
+
+    // Structures with random variants have several palettes, the first one is used.
+    let palette = match root.get("palette") {
+        Some(palette) => palette,
+        None => get(root, "palettes")?
+            .as_list()
+            .and_then(|palettes| palettes.first())
+            .ok_or("`palettes` is empty")?,
+    };
+    let palette = palette
+        .as_list()
+        .ok_or("the palette isn't a list")?
+        .iter()
+        .map(|state| {
+            get(state, "Name")?
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| "a block state without a name".to_string())
+        })
+        .collect::<Result<_, _>>()?;
+
+    let blocks = get(root, "blocks")?
+        .as_list()
+        .ok_or("`blocks` isn't a list")?
+        .iter()
+        .map(|block| {
+            let state = get(block, "state")?
+                .as_i64()
+                .and_then(|state| usize::try_from(state).ok())
+                .ok_or("invalid block state")?;
+            Ok((read_position(block, "pos")?, state))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(Schematic {
+        size,
+        palette,
+        blocks,
+    })
+}
+
+/// Reads the unsigned LEB128 numbers Sponge schematics store the blocks as.
+fn read_varints(bytes: &[i8]) -> Result<Vec<usize>, String> {
+    let mut values = vec![];
+    let mut value = 0;
+    let mut shift = 0;
+
+    for &byte in bytes {
+        let byte = byte as u8;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            values.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+            if shift > 28 {
+                return Err("block data has a number that is too big".to_string());
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+/// Reads a Sponge schematic.
+fn parse_sponge(root: &NbtTag) -> Result<Schematic, String> {
+    // Version 3 keeps everything inside a `Schematic` compound.
+    let schematic = root.get("Schematic").unwrap_or(root);
+    let size = [
+        get_usize(schematic, "Width")?,
+        get_usize(schematic, "Height")?,
+        get_usize(schematic, "Length")?,
+    ];
+    check_size(size)?;
+
+    // Version 3 moved the palette and the data to the `Blocks` compound.
+    let (palette, data) = match schematic.get("Blocks") {
+        Some(blocks) => (get(blocks, "Palette")?, get(blocks, "Data")?),
+        None => (get(schematic, "Palette")?, get(schematic, "BlockData")?),
+    };
+
+    let entries = palette.as_compound().ok_or("`Palette` isn't a compound")?;
+    let mut states = vec![String::new(); entries.len()];
+    for (state, index) in entries {
+        let index = index
+            .as_i64()
+            .and_then(|index| usize::try_from(index).ok())
+            .filter(|index| *index < states.len())
+            .ok_or_else(|| format!("invalid palette index for `{state}`"))?;
+        states[index] = state.clone();
+    }
+
+    let data = read_varints(
+        data.as_byte_array()
+            .ok_or("block data isn't a byte array")?,
+    )?;
+    let [width, height, length] = size;
+    if data.len() != width * height * length {
+        return Err(format!(
+            "has {} blocks instead of {}",
+            data.len(),
+            width * height * length
+        ));
+    }
+
+    // Blocks are stored in YZX order
+    let blocks = data
+        .into_iter()
+        .enumerate()
+        .map(|(i, state)| {
+            let x = i % width;
+            let z = (i / width) % length;
+            let y = i / (width * length);
+            ([x, y, z], state)
+        })
+        .collect();
+
+    Ok(Schematic {
+        size,
+        palette: states,
+        blocks,
+    })
+}
+
+impl Schematic {
+    /// Reads the contents of a structure or Sponge schematic file.
+    pub fn from_nbt(root: &NbtTag) -> Result<Schematic, String> {
+        let is_structure = root.get("blocks").is_some() && root.get("size").is_some();
+        let schematic = if is_structure {
+            parse_structure(root)?
+        } else {
+            parse_sponge(root)?
+        };
+
+        let [width, height, depth] = schematic.size;
+        let outside = schematic.blocks.iter().find(|([x, y, z], state)| {
+            *x >= width || *y >= height || *z >= depth || *state >= schematic.palette.len()
+        });
+        if let Some((position, state)) = outside {
+            return Err(format!(
+                "the block at {position:?} with state {state} is outside of the build"
+            ));
+        }
+
+        Ok(schematic)
+    }
+
+    /// Builds a grid of blocks of `block_size` whose corner with the smallest coordinates is `origin`.
+    pub fn to_voxel_grid(
+        &self,
+        origin: Vec3,
+        block_size: f32,
+        materials: &BlockMaterials,
+    ) -> VoxelGrid {
+        let mut grid = VoxelGrid::new(origin, self.size, block_size);
+        let block_ids: Vec<_> = materials
+            .palette_materials(self.palette.iter().map(String::as_str))
+            .into_iter()
+            .map(|material| material.map(|material| grid.register_material(material)))
+            .collect();
+
+        for ([x, y, z], state) in &self.blocks {
+            if let Some(id) = block_ids[*state] {
+                grid.set_block_id(*x, *y, *z, Some(id));
+            }
+        }
+
+        grid
+    }
+}
+
+/// Reads the structure (`.nbt`) or Sponge schematic (`.schem`) at `file_path`.
+pub fn read_schematic(file_path: impl AsRef<Path>) -> Result<Schematic, SchematicError> {
+    let file_path = file_path.as_ref();
+    let file = File::open(file_path).map_err(|e| SchematicError::Io(file_path.to_path_buf(), e))?;
+    let (_, root) = read_gzip_nbt(BufReader::new(file))
+        .map_err(|e| SchematicError::Nbt(file_path.to_path_buf(), e))?;
+
+    Schematic::from_nbt(&root)
+        .map_err(|reason| SchematicError::Invalid(file_path.to_path_buf(), reason))
+}
+
+/// Reads the structure or Sponge schematic at `file_path` as a grid of blocks of `block_size`
+/// whose corner with the smallest coordinates is `origin`.
+pub fn load_schematic(
+    file_path: impl AsRef<Path>,
+    origin: Vec3,
+    block_size: f32,
+    materials: &BlockMaterials,
+) -> Result<VoxelGrid, SchematicError> {
+    Ok(read_schematic(file_path)?.to_voxel_grid(origin, block_size, materials))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::material::{OBSIDIAN, PORTAL};
+
+    fn compound(fields: Vec<(&str, NbtTag)>) -> NbtTag {
+        NbtTag::Compound(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect::<HashMap<_, _>>(),
+        )
+    }
+
+    fn ints(values: [i32; 3]) -> NbtTag {
+        NbtTag::List(values.map(NbtTag::Int).to_vec())
+    }
+
+    #[test]
+    fn test_reads_structures() {
+        let state = |name: &str| compound(vec![("Name", NbtTag::String(name.to_string()))]);
+        let block = |pos, state| compound(vec![("pos", ints(pos)), ("state", NbtTag::Int(state))]);
+        let root = compound(vec![
+            ("size", ints([2, 3, 1])),
+            (
+                "palette",
+                NbtTag::List(vec![
+                    state("minecraft:obsidian"),
+                    state("minecraft:nether_portal"),
+                ]),
+            ),
+            (
+                "blocks",
+                NbtTag::List(vec![block([0, 0, 0], 0), block([1, 2, 0], 1)]),
+            ),
+        ]);
+
+        let grid = Schematic::from_nbt(&root).unwrap().to_voxel_grid(
+            Vec3::zeros(),
+            1.0,
+            &BlockMaterials::default(),
+        );
+
+        assert_eq!(grid.dimensions(), [2, 3, 1]);
+        assert_eq!(grid.get_block(0, 0, 0), Some(&OBSIDIAN));
+        assert_eq!(grid.get_block(1, 2, 0), Some(&PORTAL));
+        assert_eq!(grid.block_count(), 2);
+    }
+
+    #[test]
+    fn test_reads_sponge_schematics() {
+        let palette = compound(vec![
+            ("minecraft:air", NbtTag::Int(0)),
+            ("minecraft:obsidian", NbtTag::Int(1)),
+        ]);
+        // 2x1x2 with obsidian at x = 1, z = 1
+        let data = NbtTag::ByteArray(vec![0, 0, 0, 1]);
+        let v2 = compound(vec![
+            ("Width", NbtTag::Short(2)),
+            ("Height", NbtTag::Short(1)),
+            ("Length", NbtTag::Short(2)),
+            ("Palette", palette.clone()),
+            ("BlockData", data.clone()),
+        ]);
+        let v3 = compound(vec![(
+            "Schematic",
+            compound(vec![
+                ("Width", NbtTag::Short(2)),
+                ("Height", NbtTag::Short(1)),
+                ("Length", NbtTag::Short(2)),
+                (
+                    "Blocks",
+                    compound(vec![("Palette", palette), ("Data", data)]),
+                ),
+            ]),
+        )]);
+
+        for root in [v2, v3] {
+            let schematic = Schematic::from_nbt(&root).unwrap();
+            assert_eq!(schematic.size, [2, 1, 2]);
+            assert_eq!(schematic.palette[1], "minecraft:obsidian");
+            assert_eq!(schematic.blocks[3], ([1, 0, 1], 1));
+        }
+    }
+
+    #[test]
+    fn test_rejects_huge_sizes() {
+        let root = compound(vec![
+            ("size", ints([100_000, 100_000, 100_000])),
+            ("palette", NbtTag::List(vec![])),
+            ("blocks", NbtTag::List(vec![])),
+        ]);
+
+        let error = Schematic::from_nbt(&root).unwrap_err();
+
+        assert!(error.contains("[100000, 100000, 100000]"));
+    }
+
+    #[test]
+    fn test_reads_varints() {
+        let bytes = [0x05, 0x80u8 as i8, 0x01, 0xAC_u8 as i8, 0x02];
+        assert_eq!(read_varints(&bytes).unwrap(), vec![5, 128, 300]);
+    }
+}
@@ -1,6 +1,7 @@
 use glm::{Vec2, Vec3};
 
 use crate::{
+    cube::BoxBounds,
     material::Material,
     raytracer::{Intersect, Traceable},
     texture::CubeFace,
@@ -22,6 +23,14 @@ impl PartialEq for Sphere {
 impl Eq for Sphere {}
 
 impl Traceable for Sphere {
+    fn bounding_box(&self) -> BoxBounds {
+        let radius = Vec3::repeat(self.radius);
+        BoxBounds {
+            min: self.center - radius,
+            max: self.center + radius,
+        }
+    }
+
     fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Option<Intersect> {
         let oc = ray_origin - self.center;
 
@@ -0,0 +1,273 @@
+//! Generates hilly terrain out of fractal noise.
+//!
+//! The height of every column comes from fractal (fBm) Perlin or Simplex noise. Columns are
+//! filled with layers of materials from the surface down, caves are carved where a 3D noise
+//! is high enough and the valleys under the sea level are flooded with `WATER`.
+//! The same settings, seed included, always generate the same terrain.
+
+use glm::Vec3;
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin, Simplex};
+
+use crate::{
+    material::{Material, DIRT, MOSS, NETHERRACK, OBSIDIAN, STONE, WATER},
+    voxel::VoxelGrid,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoiseKind {
+    #[default]
+    Perlin,
+    Simplex,
+}
+
+impl NoiseKind {
+    /// Fractal noise made of `octaves` layers of this noise, with values between -1 and 1.
+    fn fractal<const DIM: usize>(
+        &self,
+        seed: u32,
+        octaves: usize,
+        frequency: f64,
+    ) -> Box<dyn NoiseFn<f64, DIM>>
+    where
+        Fbm<Perlin>: NoiseFn<f64, DIM>,
+        Fbm<Simplex>: NoiseFn<f64, DIM>,
+    {
+        match self {
+            NoiseKind::Perlin => Box::new(
+                Fbm::<Perlin>::new(seed)
+                    .set_octaves(octaves)
+                    .set_frequency(frequency),
+            ),
+            NoiseKind::Simplex => Box::new(
+                Fbm::<Simplex>::new(seed)
+                    .set_octaves(octaves)
+                    .set_frequency(frequency),
+            ),
+        }
+    }
+}
+
+/// A layer of the ground.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stratum {
+    /// How many blocks thick the layer is.
+    pub thickness: usize,
+    pub material: Material,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainSettings {
+    /// How many blocks wide (x) and deep (z) the terrain is.
+    pub size: [usize; 2],
+    /// How many blocks tall the terrain can get.
+    pub height: usize,
+    pub seed: u32,
+    pub noise: NoiseKind,
+    /// Layers of noise added together, each one with smaller details than the previous one.
+    pub octaves: usize,
+    /// How often the hills repeat, about one hill every `1 / frequency` blocks.
+    pub frequency: f64,
+    /// Height of the ground where the noise is 0, as a fraction of `height`.
+    pub base_height: f64,
+    /// How far over and under the base height the ground goes, as a fraction of `height`.
+    pub roughness: f64,
+    /// Empty cells under this height are filled with water, 0 leaves no water.
+    pub sea_level: usize,
+    /// Layers from the surface down, the last one fills the rest of the column.
+    /// Columns under the sea start at the second layer, the first one is like grass.
+    pub strata: Vec<Stratum>,
+    /// Material of the bottom layer, caves never go through it.
+    pub bedrock: Option<Material>,
+    /// Blocks where the cave noise is over this value are carved out,
+    /// values of 1 or more disable caves.
+    pub cave_threshold: f64,
+    /// How often caves repeat, like `frequency`.
+    pub cave_frequency: f64,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        TerrainSettings {
+            size: [48, 48],
+            height: 24,
+            seed: 0,
+            noise: NoiseKind::Perlin,
+            octaves: 4,
+            frequency: 1.0 / 24.0,
+            base_height: 0.45,
+            roughness: 0.35,
+            sea_level: 10,
+            strata: vec![
+                Stratum {
+                    thickness: 1,
+                    material: MOSS,
+                },
+                Stratum {
+                    thickness: 3,
+                    material: DIRT,
+                },
+                Stratum {
+                    thickness: 8,
+                    material: STONE,
+                },
+                Stratum {
+                    thickness: 1,
+                    material: NETHERRACK,
+                },
+            ],
+            bedrock: Some(OBSIDIAN),
+            cave_threshold: 0.35,
+            cave_frequency: 1.0 / 12.0,
+        }
+    }
+}
+
+impl TerrainSettings {
+    /// The height of the ground of every column, in x-major order.
+    pub fn heightmap(&self) -> Vec<usize> {
+        let [width, depth] = self.size;
+        let noise = self
+            .noise
+            .fractal::<2>(self.seed, self.octaves, self.frequency);
+        let top = self.height.saturating_sub(1) as f64;
+
+        (0..depth)
+            .flat_map(|z| (0..width).map(move |x| (x, z)))
+            .map(|(x, z)| {
+                let value = noise.get([x as f64, z as f64]);
+                let height = (self.base_height + self.roughness * value) * self.height as f64;
+                height.round().clamp(0.0, top) as usize
+            })
+            .collect()
+    }
+}
+
+/// Generates a terrain whose corner with the smallest coordinates is `origin`.
+pub fn generate_terrain(origin: Vec3, block_size: f32, settings: &TerrainSettings) -> VoxelGrid {
+    let [width, depth] = settings.size;
+    let height = settings.height;
+    let mut grid = VoxelGrid::new(origin, [width, height, depth], block_size);
+    if height == 0 || settings.strata.is_empty() {
+        return grid;
+    }
+
+    let strata: Vec<_> = settings
+        .strata
+        .iter()
+        .map(|stratum| {
+            (
+                stratum.thickness,
+                grid.register_material(stratum.material.clone()),
+            )
+        })
+        .collect();
+    let bedrock = settings
+        .bedrock
+        .clone()
+        .map(|material| grid.register_material(material));
+    let water = grid.register_material(WATER);
+
+    let caves = settings.cave_threshold < 1.0;
+    // Another seed so caves don't follow the hills
+    let cave_noise =
+        settings
+            .noise
+            .fractal::<3>(settings.seed.wrapping_add(1), 2, settings.cave_frequency);
+
+    let heightmap = settings.heightmap();
+    for z in 0..depth {
+        for x in 0..width {
+            let surface = heightmap[z * width + x];
+            let underwater = surface < settings.sea_level;
+            let column_strata = match underwater && strata.len() > 1 {
+                true => &strata[1..],
+                false => &strata[..],
+            };
+
+            let mut layer = 0;
+            let mut layer_bottom = surface as i64 - column_strata[0].0 as i64;
+            for y in (0..=surface).rev() {
+                while (y as i64) <= layer_bottom && layer + 1 < column_strata.len() {
+                    layer += 1;
+                    layer_bottom -= column_strata[layer].0 as i64;
+                }
+
+                let block = match bedrock {
+                    Some(bedrock) if y == 0 => bedrock,
+                    _ => column_strata[layer].1,
+                };
+                grid.set_block_id(x, y, z, Some(block));
+            }
+
+            if caves {
+                // Caves under the sea keep a roof so they don't open in the sea floor.
+                let roof = if underwater {
+                    surface.saturating_sub(1)
+                } else {
+                    surface + 1
+                };
+                let floor = if bedrock.is_some() { 1 } else { 0 };
+                for y in floor..roof {
+                    let point = [x as f64, y as f64, z as f64];
+                    if cave_noise.get(point) > settings.cave_threshold {
+                        grid.set_block_id(x, y, z, None);
+                    }
+                }
+            }
+
+            for y in surface + 1..settings.sea_level.min(height) {
+                grid.set_block_id(x, y, z, Some(water));
+            }
+        }
+    }
+
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_terrain() {
+        let settings = TerrainSettings {
+            size: [16, 16],
+            ..TerrainSettings::default()
+        };
+        let other_seed = TerrainSettings {
+            seed: 7,
+            ..settings.clone()
+        };
+
+        assert_eq!(settings.heightmap(), settings.clone().heightmap());
+        assert_ne!(settings.heightmap(), other_seed.heightmap());
+    }
+
+    #[test]
+    fn test_layers_and_sea() {
+        let settings = TerrainSettings {
+            size: [16, 16],
+            cave_threshold: 1.0,
+            ..TerrainSettings::default()
+        };
+        let grid = generate_terrain(Vec3::zeros(), 1.0, &settings);
+        let heightmap = settings.heightmap();
+
+        for (i, &surface) in heightmap.iter().enumerate() {
+            let (x, z) = (i % 16, i / 16);
+            assert_eq!(grid.get_block(x, 0, z), Some(&OBSIDIAN));
+            if surface > 4 {
+                assert_eq!(grid.get_block(x, surface - 2, z), Some(&DIRT));
+            }
+            if surface >= settings.sea_level {
+                assert_eq!(grid.get_block(x, surface, z), Some(&MOSS));
+            } else {
+                assert_eq!(grid.get_block(x, settings.sea_level - 1, z), Some(&WATER));
+            }
+            assert_eq!(
+                grid.get_block(x, surface.max(settings.sea_level - 1) + 1, z),
+                None
+            );
+        }
+    }
+}
@@ -1,14 +1,54 @@
-use std::{fs::File, io::BufReader};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Cursor, Seek},
+    path::{Path, PathBuf},
+};
 
 use glm::Vec2;
 use image::{
-    codecs::gif::GifDecoder, AnimationDecoder, Frame, GenericImageView, ImageDecoder, ImageReader,
-    Pixel,
+    codecs::gif::GifDecoder, AnimationDecoder, DynamicImage, Frame, GenericImageView, ImageDecoder,
+    ImageError, ImageFormat, ImageReader, Pixel,
 };
+use include_dir::{include_dir, Dir};
+use serde::Deserialize;
 
-use crate::color::Color;
+use crate::{
+    color::Color,
+    resource_pack::{is_resource_pack, load_resource_pack},
+};
 
 #[derive(Debug)]
+pub enum TextureError {
+    Io(PathBuf, io::Error),
+    Decode(PathBuf, ImageError),
+    Manifest(PathBuf, serde_json::Error),
+    Animation(PathBuf, serde_json::Error),
+}
+
+impl Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::Io(path, error) => {
+                write!(f, "Couldn't read texture {}: {error}", path.display())
+            }
+            TextureError::Decode(path, error) => {
+                write!(f, "Couldn't decode texture {}: {error}", path.display())
+            }
+            TextureError::Manifest(path, error) => {
+                write!(f, "Invalid texture manifest {}: {error}", path.display())
+            }
+            TextureError::Animation(path, error) => {
+                write!(f, "Invalid animation {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CubeFace {
     NONE,
     TOP,
@@ -19,89 +59,439 @@ pub enum CubeFace {
     RIGHT,
 }
 
-pub struct GameTextures {
-    pub dirt: Texture,
-    pub stone: Texture,
-    pub moss: Texture,
-    pub water: Texture,
-    pub obsidian: Texture,
-    pub portal: Texture,
-    pub netherrack: Texture,
-    pub magma: Texture,
-    pub gold: Texture,
-}
-
-#[derive(Debug, Clone, Copy)]
-pub enum Textures {
-    DIRT,
-    STONE,
-    MOSS,
-    WATER,
-    OBSIDIAN,
-    PORTAL,
-    NETHERRACK,
-    MAGMA,
-    GOLD,
-}
-
-impl GameTextures {
-    pub fn new(asset_dir: &str) -> Self {
-        let dirt = format!("{asset_dir}dirt.png");
-        let stone = format!("{asset_dir}stone.png");
-        let moss = format!("{asset_dir}moss.png");
-        let water = format!("{asset_dir}water.png");
-        let obsidian = format!("{asset_dir}obsidian.png");
-        let portal = format!("{asset_dir}portal.png");
-        let netherrack = format!("{asset_dir}netherrack.png");
-        let magma = format!("{asset_dir}magma.png");
-        let gold = format!("{asset_dir}gold.png");
-
-        let dirt = Texture::new(&dirt, 16);
-        let stone = Texture::new(&stone, 16);
-        let moss = Texture::new(&moss, 16);
-        let water = Texture::new(&water, 16);
-        let obsidian = Texture::new(&obsidian, 16);
-        let portal = Texture::new(&portal, 16);
-        let netherrack = Texture::new(&netherrack, 16);
-        let magma = Texture::new(&magma, 16);
-        let gold = Texture::new(&gold, 16);
-
-        GameTextures {
-            dirt,
-            stone,
-            moss,
-            water,
-            obsidian,
-            portal,
-            netherrack,
-            magma,
-            gold,
-        }
-    }
-
-    pub fn get_texture(&self, tx_type: &Textures) -> &Texture {
-        match tx_type {
-            Textures::DIRT => &self.dirt,
-            Textures::STONE => &self.stone,
-            Textures::MOSS => &self.moss,
-            Textures::WATER => &self.water,
-            Textures::OBSIDIAN => &self.obsidian,
-            Textures::PORTAL => &self.portal,
-            Textures::NETHERRACK => &self.netherrack,
-            Textures::MAGMA => &self.magma,
-            Textures::GOLD => &self.gold,
+/// Names of the textures every registry has, in the order of their `TextureId`s.
+const BUILTIN_TEXTURES: [&str; 9] = [
+    "dirt",
+    "stone",
+    "moss",
+    "water",
+    "obsidian",
+    "portal",
+    "netherrack",
+    "magma",
+    "gold",
+];
+
+/// The file inside an asset directory that gives names to more textures.
+const MANIFEST_FILE: &str = "textures.json";
+
+/// The default textures, compiled into the binary so it works without an asset directory.
+static DEFAULT_ASSETS: Dir = include_dir!("$CARGO_MANIFEST_DIR/imgs");
+
+/// Handle of a texture of a `TextureRegistry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureId(usize);
+
+impl TextureId {
+    pub const DIRT: TextureId = TextureId(0);
+    pub const STONE: TextureId = TextureId(1);
+    pub const MOSS: TextureId = TextureId(2);
+    pub const WATER: TextureId = TextureId(3);
+    pub const OBSIDIAN: TextureId = TextureId(4);
+    pub const PORTAL: TextureId = TextureId(5);
+    pub const NETHERRACK: TextureId = TextureId(6);
+    pub const MAGMA: TextureId = TextureId(7);
+    pub const GOLD: TextureId = TextureId(8);
+}
+
+/// The texture of every face of a block, named after the directions of Minecraft:
+/// north is towards -z, south +z, west -x and east +x.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockFaces {
+    pub up: TextureId,
+    pub down: TextureId,
+    pub north: TextureId,
+    pub south: TextureId,
+    pub west: TextureId,
+    pub east: TextureId,
+}
+
+impl BlockFaces {
+    /// The same texture on every face.
+    pub fn all(texture: TextureId) -> Self {
+        BlockFaces {
+            up: texture,
+            down: texture,
+            north: texture,
+            south: texture,
+            west: texture,
+            east: texture,
+        }
+    }
+
+    pub fn face(&self, face: &CubeFace) -> TextureId {
+        match face {
+            CubeFace::TOP => self.up,
+            CubeFace::BOTTOM => self.down,
+            CubeFace::BACKWARDS | CubeFace::NONE => self.north,
+            CubeFace::FORWARDS => self.south,
+            CubeFace::LEFT => self.west,
+            CubeFace::RIGHT => self.east,
+        }
+    }
+
+    fn map(self, f: impl Fn(TextureId) -> TextureId) -> Self {
+        BlockFaces {
+            up: f(self.up),
+            down: f(self.down),
+            north: f(self.north),
+            south: f(self.south),
+            west: f(self.west),
+            east: f(self.east),
         }
     }
 }
 
 #[derive(Debug)]
+enum Entry {
+    Image(Texture),
+    /// A different image for every face, the ids are always of images.
+    Block(BlockFaces),
+}
+
+/// Block textures by name.
+///
+/// A name is either an image (a single one or the unwrap of a cube, see `Texture`) or a
+/// block with an image for each face, see `insert_block`.
+///
+/// The built-in textures (`dirt`, `stone`, ...) always have the ids of the constants of
+/// `TextureId` so the predefined materials can use them, until they are loaded they are
+/// `Texture::missing`. Any other name gets a new id the first time it's inserted.
+#[derive(Debug)]
+pub struct TextureRegistry {
+    entries: Vec<Entry>,
+    ids: HashMap<String, TextureId>,
+    /// Stands in for the faces whose image was replaced by a block.
+    missing: Texture,
+}
+
+impl Default for TextureRegistry {
+    fn default() -> Self {
+        let mut registry = TextureRegistry {
+            entries: Vec::with_capacity(BUILTIN_TEXTURES.len()),
+            ids: HashMap::new(),
+            missing: Texture::missing(),
+        };
+        for name in BUILTIN_TEXTURES {
+            registry.insert(name, Texture::missing());
+        }
+
+        registry
+    }
+}
+
+impl TextureRegistry {
+    /// The default textures, the images of `imgs/` compiled into the binary.
+    pub fn builtin() -> Self {
+        let mut registry = TextureRegistry::default();
+
+        let mut files: Vec<_> = DEFAULT_ASSETS
+            .files()
+            .filter(|file| ImageFormat::from_path(file.path()).is_ok())
+            .collect();
+        files.sort_by_key(|file| file.path());
+        for file in files {
+            let mcmeta = DEFAULT_ASSETS
+                .get_file(mcmeta_path(file.path()))
+                .and_then(|mcmeta| mcmeta.contents_utf8());
+            let texture = Texture::decode_with_mcmeta(file.path(), file.contents(), mcmeta);
+            registry.insert_or_missing(&texture_name(file.path()), texture);
+        }
+
+        registry
+    }
+
+    /// The default textures with the ones of `asset_dir` on top, see `load_dir`.
+    /// If `asset_dir` is a Minecraft resource pack its blocks are loaded instead,
+    /// see `resource_pack`.
+    pub fn new(asset_dir: impl AsRef<Path>) -> Self {
+        let asset_dir = asset_dir.as_ref();
+        let mut registry = TextureRegistry::builtin();
+        if is_resource_pack(asset_dir) {
+            if let Err(error) = load_resource_pack(&mut registry, asset_dir) {
+                eprintln!("Warning: ignoring the resource pack. {error}");
+            }
+        } else {
+            registry.load_dir(asset_dir);
+        }
+        registry
+    }
+
+    /// Loads every image inside `asset_dir`, named after its file without the extension
+    /// (e.g. `stone.png` is `stone`), replacing the textures with the same names.
+    ///
+    /// The directory may also have a `textures.json` manifest that names more images by
+    /// their path relative to it, like `{ "oak_planks": "blocks/oak_planks.png" }`.
+    ///
+    /// Textures that can't be loaded are replaced by `Texture::missing` with a warning so
+    /// there's always something to render.
+    pub fn load_dir(&mut self, asset_dir: impl AsRef<Path>) {
+        let asset_dir = asset_dir.as_ref();
+
+        let mut files: Vec<PathBuf> = match fs::read_dir(asset_dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && ImageFormat::from_path(path).is_ok())
+                .collect(),
+            Err(error) => {
+                eprintln!(
+                    "Warning: couldn't read the asset directory {}: {error}",
+                    asset_dir.display()
+                );
+                vec![]
+            }
+        };
+        // The same directory always gives the same ids
+        files.sort();
+        for file in files {
+            self.insert_or_missing(&texture_name(&file), Texture::load(&file));
+        }
+
+        let manifest = asset_dir.join(MANIFEST_FILE);
+        match read_manifest(&manifest) {
+            Ok(entries) => {
+                for (name, file) in entries {
+                    self.insert_or_missing(&name, Texture::load(asset_dir.join(file)));
+                }
+            }
+            Err(error) => eprintln!("Warning: ignoring the texture manifest. {error}"),
+        }
+    }
+
+    /// Inserts a loaded texture, or `Texture::missing` with a warning if it couldn't be loaded.
+    fn insert_or_missing(
+        &mut self,
+        name: &str,
+        texture: Result<Texture, TextureError>,
+    ) -> TextureId {
+        let texture = texture.unwrap_or_else(|error| {
+            eprintln!("Warning: using a placeholder for the `{name}` texture. {error}");
+            Texture::missing()
+        });
+        self.insert(name, texture)
+    }
+
+    /// Adds a texture, or replaces the one with the same name, and gives back its id.
+    /// Names ignore case.
+    pub fn insert(&mut self, name: &str, texture: Texture) -> TextureId {
+        self.insert_entry(name, Entry::Image(texture))
+    }
+
+    /// Adds a block with a texture on each face, or replaces the texture with the same name,
+    /// and gives back its id. Faces that are blocks themselves use their north face.
+    pub fn insert_block(&mut self, name: &str, faces: BlockFaces) -> TextureId {
+        let faces = faces.map(|face| match &self.entries[face.0] {
+            Entry::Image(_) => face,
+            Entry::Block(faces) => faces.north,
+        });
+        self.insert_entry(name, Entry::Block(faces))
+    }
+
+    fn insert_entry(&mut self, name: &str, entry: Entry) -> TextureId {
+        let name = name.to_lowercase();
+        match self.ids.get(&name) {
+            Some(&id) => {
+                self.entries[id.0] = entry;
+                id
+            }
+            None => {
+                let id = TextureId(self.entries.len());
+                self.entries.push(entry);
+                self.ids.insert(name, id);
+                id
+            }
+        }
+    }
+
+    /// Gets the id of a texture by its name, ignoring case.
+    pub fn id(&self, name: &str) -> Option<TextureId> {
+        self.ids.get(&name.to_lowercase()).copied()
+    }
+
+    /// The image of a texture, the one of the north face for blocks.
+    pub fn get(&self, id: TextureId) -> &Texture {
+        match &self.entries[id.0] {
+            Entry::Image(texture) => texture,
+            Entry::Block(faces) => match &self.entries[faces.north.0] {
+                Entry::Image(texture) => texture,
+                Entry::Block(_) => &self.missing,
+            },
+        }
+    }
+
+    /// The faces of a texture if it's a block.
+    pub fn block_faces(&self, id: TextureId) -> Option<BlockFaces> {
+        match &self.entries[id.0] {
+            Entry::Image(_) => None,
+            Entry::Block(faces) => Some(*faces),
+        }
+    }
+
+    /// The color of the texture on `face` at `uv`, the texture coordinates of the point of
+    /// the face (see `cube::face_attributes`), `time` seconds into its animation.
+    pub fn face_color(&self, id: TextureId, face: &CubeFace, uv: &Vec2, time: f32) -> Color {
+        match self.block_faces(id) {
+            Some(faces) => self
+                .get(faces.face(face))
+                .sample_at(&upright_uv(face, uv), time),
+            None => {
+                let texture = self.get(id);
+                let sprite_size = texture.sprite_size as f32;
+                texture.get_color_of_face(face, uv.x * sprite_size, uv.y * sprite_size, time)
+            }
+        }
+    }
+
+    /// Whether the texture changes with time, on any face for blocks.
+    pub fn is_animated(&self, id: TextureId) -> bool {
+        match &self.entries[id.0] {
+            Entry::Image(texture) => texture.is_animated(),
+            Entry::Block(faces) => [
+                faces.up,
+                faces.down,
+                faces.north,
+                faces.south,
+                faces.west,
+                faces.east,
+            ]
+            .into_iter()
+            .any(|face| self.get(face).is_animated()),
+        }
+    }
+
+    /// The shortest `Texture::animation_step` of all the textures, `None` if no texture is animated.
+    pub fn animation_step(&self) -> Option<f32> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Image(texture) => texture.animation_step(),
+                Entry::Block(_) => None,
+            })
+            .reduce(f32::min)
+    }
+
+    /// The names of every texture with their ids.
+    pub fn names(&self) -> impl Iterator<Item = (&str, TextureId)> {
+        self.ids.iter().map(|(name, &id)| (name.as_str(), id))
+    }
+}
+
+/// A texture made of the pixels of an image, or of its first frame if it's animated.
+#[derive(Debug, PartialEq)]
 pub struct Texture {
     pub width: u32,
     pub height: u32,
     pub sprite_size: usize,
     colors: Vec<Color>,
+    animation: Option<Animation>,
+}
+
+/// The frames of an animated texture and when each one is shown.
+#[derive(Debug, PartialEq)]
+struct Animation {
+    /// The pixels of every frame, in row order like the ones of the texture.
+    frames: Vec<Vec<Color>>,
+    /// Frames in the order they are shown, with how many seconds each one lasts.
+    timeline: Vec<(usize, f32)>,
+    /// Whether every frame fades into the next one instead of switching at once.
+    interpolate: bool,
+}
+
+impl Animation {
+    /// An animation of the frames that are shown in `timeline`, `None` if it doesn't change.
+    fn new(
+        frames: Vec<Vec<Color>>,
+        timeline: Vec<(usize, f32)>,
+        interpolate: bool,
+    ) -> Option<Self> {
+        let timeline: Vec<_> = timeline
+            .into_iter()
+            .filter(|&(frame, duration)| frame < frames.len() && duration > 0.0)
+            .collect();
+        match timeline.len() > 1 {
+            true => Some(Animation {
+                frames,
+                timeline,
+                interpolate,
+            }),
+            false => None,
+        }
+    }
+
+    /// The frame shown `time` seconds into the animation, the one after it and how far it
+    /// has faded into it (from 0 to 1). The animation loops forever.
+    fn frames_at(&self, time: f32) -> (usize, usize, f32) {
+        let duration: f32 = self.timeline.iter().map(|(_, duration)| duration).sum();
+        let mut time = time.rem_euclid(duration);
+        for (i, &(frame, frame_duration)) in self.timeline.iter().enumerate() {
+            if time < frame_duration || i + 1 == self.timeline.len() {
+                let next = self.timeline[(i + 1) % self.timeline.len()].0;
+                let fade = match self.interpolate {
+                    true => (time / frame_duration).clamp(0.0, 1.0),
+                    false => 0.0,
+                };
+                return (frame, next, fade);
+            }
+            time -= frame_duration;
+        }
+        (self.timeline[0].0, self.timeline[0].0, 0.0)
+    }
+
+    /// The most time that can pass without the animation looking different, in seconds.
+    /// Interpolated frames change `FADE_STEPS` times while they fade into the next one.
+    fn step(&self) -> f32 {
+        let shortest = self
+            .timeline
+            .iter()
+            .map(|&(_, duration)| duration)
+            .fold(f32::INFINITY, f32::min);
+        match self.interpolate {
+            true => shortest / FADE_STEPS,
+            false => shortest,
+        }
+    }
+}
+
+/// The `.mcmeta` file that makes an image an animation in Minecraft.
+#[derive(Deserialize)]
+struct AnimationMeta {
+    animation: AnimationDef,
+}
+
+#[derive(Deserialize)]
+struct AnimationDef {
+    #[serde(default)]
+    interpolate: bool,
+    /// Size of the frames, they are square and as wide as the image by default.
+    width: Option<u32>,
+    height: Option<u32>,
+    /// Ticks every frame lasts.
+    #[serde(default = "default_frametime")]
+    frametime: u32,
+    /// Order of the frames, every frame once from the top by default.
+    frames: Option<Vec<FrameDef>>,
+}
+
+fn default_frametime() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FrameDef {
+    Index(usize),
+    Timed { index: usize, time: u32 },
 }
 
+/// Seconds in a Minecraft tick, the unit of the times of `.mcmeta` files.
+const TICK: f32 = 1.0 / 20.0;
+
+/// How long GIF frames without a delay last, like in web browsers.
+const DEFAULT_GIF_DELAY: f32 = 0.1;
+
+/// How many different mixes of two frames an interpolated animation shows while it fades.
+const FADE_STEPS: f32 = 16.0;
+
 pub struct AnimatedTexture {
     pub width: u32,
     pub height: u32,
@@ -110,20 +500,32 @@ pub struct AnimatedTexture {
 }
 
 impl AnimatedTexture {
-    pub fn new(file_path: &str) -> Self {
-        let file_in = BufReader::new(File::open(file_path).unwrap());
-        let decoder = GifDecoder::new(file_in).unwrap();
+    pub fn new(file_path: impl AsRef<Path>) -> Result<Self, TextureError> {
+        let file_path = file_path.as_ref();
+        let file =
+            File::open(file_path).map_err(|e| TextureError::Io(file_path.to_path_buf(), e))?;
+        AnimatedTexture::from_reader(file_path, BufReader::new(file))
+    }
+
+    /// Like `new`, from the contents of a GIF file. `file_path` only names it in errors.
+    pub fn decode(file_path: impl AsRef<Path>, contents: &[u8]) -> Result<Self, TextureError> {
+        AnimatedTexture::from_reader(file_path.as_ref(), Cursor::new(contents))
+    }
+
+    fn from_reader(file_path: &Path, reader: impl BufRead + Seek) -> Result<Self, TextureError> {
+        let decode_error = |error| TextureError::Decode(file_path.to_path_buf(), error);
+        let decoder = GifDecoder::new(reader).map_err(decode_error)?;
         let (width, height) = decoder.dimensions();
         let frames = decoder.into_frames();
-        let frames = frames.collect_frames().expect("error decoding gif");
+        let frames = frames.collect_frames().map_err(decode_error)?;
         let frame_count = frames.len();
 
-        Self {
+        Ok(Self {
             width,
             height,
             frames,
             frame_count,
-        }
+        })
     }
 
     /// Get's the color of the pixel positioned on the frame `t`.
@@ -135,11 +537,164 @@ impl AnimatedTexture {
 
         Color { r, g, b }
     }
+
+    /// How many seconds the frame `t` is shown.
+    pub fn frame_duration(&self, t: usize) -> f32 {
+        let (numerator, denominator) = self.frames[t].delay().numer_denom_ms();
+        match numerator {
+            0 => DEFAULT_GIF_DELAY,
+            _ => numerator as f32 / denominator.max(1) as f32 / 1000.0,
+        }
+    }
+}
+
+impl From<AnimatedTexture> for Texture {
+    /// Every frame of the GIF is either the unwrap of a cube or a single image, see
+    /// `Texture::load`.
+    fn from(gif: AnimatedTexture) -> Self {
+        let timeline = (0..gif.frame_count)
+            .map(|t| (t, gif.frame_duration(t)))
+            .collect();
+        let frames: Vec<Texture> = gif
+            .frames
+            .into_iter()
+            .map(|frame| Texture::from_block_image(&DynamicImage::ImageRgba8(frame.into_buffer())))
+            .collect();
+        Texture::from_frames(frames, timeline, false)
+    }
 }
 
 impl Texture {
-    pub fn new(file_path: &str, sprite_size: usize) -> Self {
-        let image = ImageReader::open(file_path).unwrap().decode().unwrap();
+    pub fn new(file_path: impl AsRef<Path>, sprite_size: usize) -> Result<Self, TextureError> {
+        let image = read_image(file_path.as_ref())?;
+        Ok(Texture::from_image(&image, sprite_size))
+    }
+
+    /// Loads a texture that's either the unwrap of a cube (3 sprites wide and 4 tall, see
+    /// `get_color_of_face`) or a single image used on every face.
+    ///
+    /// GIFs are animated. So are the images with a `.mcmeta` file next to them
+    /// (`water.png.mcmeta`), a vertical strip of frames like the animated textures of Minecraft.
+    pub fn load(file_path: impl AsRef<Path>) -> Result<Self, TextureError> {
+        let file_path = file_path.as_ref();
+        let contents =
+            fs::read(file_path).map_err(|e| TextureError::Io(file_path.to_path_buf(), e))?;
+        let mcmeta_path = mcmeta_path(file_path);
+        let mcmeta = match fs::read_to_string(&mcmeta_path) {
+            Ok(mcmeta) => Some(mcmeta),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(TextureError::Io(mcmeta_path, error)),
+        };
+        Texture::decode_with_mcmeta(file_path, &contents, mcmeta.as_deref())
+    }
+
+    /// Like `load`, from the contents of an image file. `file_path` only names it in errors.
+    pub fn decode(file_path: impl AsRef<Path>, contents: &[u8]) -> Result<Self, TextureError> {
+        Texture::decode_with_mcmeta(file_path, contents, None)
+    }
+
+    /// Like `decode`, `mcmeta` is the contents of the `.mcmeta` file of the image if it has one.
+    pub fn decode_with_mcmeta(
+        file_path: impl AsRef<Path>,
+        contents: &[u8],
+        mcmeta: Option<&str>,
+    ) -> Result<Self, TextureError> {
+        let file_path = file_path.as_ref();
+        if image::guess_format(contents).is_ok_and(|format| format == ImageFormat::Gif) {
+            return AnimatedTexture::decode(file_path, contents).map(Texture::from);
+        }
+
+        let image = image::load_from_memory(contents)
+            .map_err(|e| TextureError::Decode(file_path.to_path_buf(), e))?;
+        match mcmeta {
+            Some(mcmeta) => {
+                let meta: AnimationMeta = serde_json::from_str(mcmeta)
+                    .map_err(|e| TextureError::Animation(mcmeta_path(file_path), e))?;
+                Ok(Texture::from_strip(&image, &meta.animation))
+            }
+            None => Ok(Texture::from_block_image(&image)),
+        }
+    }
+
+    /// Splits an image into the frames of an animation as described by a `.mcmeta` file.
+    /// Frames go from left to right and then from top to bottom.
+    fn from_strip(image: &DynamicImage, animation: &AnimationDef) -> Self {
+        let (width, height) = image.dimensions();
+        let frame_width = animation.width.unwrap_or(width).clamp(1, width.max(1));
+        let frame_height = animation
+            .height
+            .unwrap_or(frame_width)
+            .clamp(1, height.max(1));
+        let columns = (width / frame_width).max(1);
+        let frame_count = (columns * (height / frame_height)).max(1);
+
+        let frames = (0..frame_count)
+            .map(|i| {
+                let x = i % columns * frame_width;
+                let y = i / columns * frame_height;
+                Texture::from_block_image(&image.crop_imm(x, y, frame_width, frame_height))
+            })
+            .collect();
+        let ticks: Vec<(usize, u32)> = match &animation.frames {
+            Some(frames) => frames
+                .iter()
+                .map(|frame| match *frame {
+                    FrameDef::Index(index) => (index, animation.frametime),
+                    FrameDef::Timed { index, time } => (index, time),
+                })
+                .collect(),
+            None => (0..frame_count as usize)
+                .map(|index| (index, animation.frametime))
+                .collect(),
+        };
+        let timeline = ticks
+            .into_iter()
+            .map(|(index, ticks)| (index, ticks as f32 * TICK))
+            .collect();
+
+        Texture::from_frames(frames, timeline, animation.interpolate)
+    }
+
+    /// An animated texture out of frames of the same size, the first one is the still image.
+    fn from_frames(frames: Vec<Texture>, timeline: Vec<(usize, f32)>, interpolate: bool) -> Self {
+        let mut frames = frames.into_iter();
+        let mut texture = frames.next().unwrap_or_else(Texture::missing);
+        let colors = std::iter::once(texture.colors.clone())
+            .chain(
+                frames
+                    .filter(|frame| (frame.width, frame.height) == (texture.width, texture.height))
+                    .map(|frame| frame.colors),
+            )
+            .collect();
+        texture.animation = Animation::new(colors, timeline, interpolate);
+        texture
+    }
+
+    fn from_block_image(image: &DynamicImage) -> Self {
+        let (width, height) = image.dimensions();
+        let sprite_size = match width % 3 == 0 && width / 3 * 4 == height {
+            true => width / 3,
+            false => width.max(height),
+        };
+        Texture::from_image(image, sprite_size as usize)
+    }
+
+    /// A magenta and black checkerboard that stands in for the textures that couldn't be loaded.
+    pub fn missing() -> Self {
+        const SIZE: u32 = 16;
+        let colors = (0..SIZE * SIZE)
+            .map(|i| {
+                let (x, y) = (i % SIZE, i / SIZE);
+                match (x < SIZE / 2) == (y < SIZE / 2) {
+                    true => Color::new(0xff, 0x00, 0xff),
+                    false => Color::new(0x00, 0x00, 0x00),
+                }
+            })
+            .collect();
+        Texture::from_colors(SIZE, SIZE, colors)
+    }
+
+    fn from_image(image: &DynamicImage, sprite_size: usize) -> Self {
         let width = image.width();
         let height = image.height();
 
@@ -165,18 +720,110 @@ impl Texture {
             height,
             colors,
             sprite_size,
+            animation: None,
         }
     }
 
+    /// Creates a texture that isn't split in sprites from its pixels in row order.
+    pub fn from_colors(width: u32, height: u32, colors: Vec<Color>) -> Self {
+        assert_eq!(colors.len(), (width * height) as usize);
+        Texture {
+            width,
+            height,
+            sprite_size: width.max(height) as usize,
+            colors,
+            animation: None,
+        }
+    }
+
+    /// The same texture multiplied by a color, every frame of it if it's animated.
+    pub fn tinted(&self, tint: Color) -> Self {
+        let tint_colors = |colors: &Vec<Color>| -> Vec<Color> {
+            colors
+                .iter()
+                .map(|color| {
+                    Color::new(
+                        (color.r as u16 * tint.r as u16 / 255) as u8,
+                        (color.g as u16 * tint.g as u16 / 255) as u8,
+                        (color.b as u16 * tint.b as u16 / 255) as u8,
+                    )
+                })
+                .collect()
+        };
+
+        Texture {
+            width: self.width,
+            height: self.height,
+            sprite_size: self.sprite_size,
+            colors: tint_colors(&self.colors),
+            animation: self.animation.as_ref().map(|animation| Animation {
+                frames: animation.frames.iter().map(tint_colors).collect(),
+                timeline: animation.timeline.clone(),
+                interpolate: animation.interpolate,
+            }),
+        }
+    }
+
+    /// Whether the texture changes with time.
+    pub fn is_animated(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// How often the animation should be sampled to show every change, in seconds.
+    /// `None` if the texture isn't animated.
+    pub fn animation_step(&self) -> Option<f32> {
+        self.animation.as_ref().map(Animation::step)
+    }
+
+    /// The color of the whole texture at `uv`, (0, 0) being its top left corner.
+    /// Coordinates outside of [0, 1] wrap around.
+    pub fn sample(&self, uv: &Vec2) -> Color {
+        self.sample_at(uv, 0.0)
+    }
+
+    /// Like `sample`, `time` seconds into the animation of the texture.
+    pub fn sample_at(&self, uv: &Vec2, time: f32) -> Color {
+        let x = (uv.x.rem_euclid(1.0) * self.width as f32) as u32;
+        let y = (uv.y.rem_euclid(1.0) * self.height as f32) as u32;
+        self.get_pixel_color_at(x.min(self.width - 1), y.min(self.height - 1), time)
+    }
+
     pub fn get_pixel_color(&self, x: u32, y: u32) -> Color {
         let idx = y * self.width + x;
         self.colors[idx as usize]
     }
 
+    /// The color of a pixel `time` seconds into the animation of the texture, textures that
+    /// aren't animated look the same at any time.
+    pub fn get_pixel_color_at(&self, x: u32, y: u32, time: f32) -> Color {
+        let Some(animation) = &self.animation else {
+            return self.get_pixel_color(x, y);
+        };
+
+        let idx = (y * self.width + x) as usize;
+        let (frame, next, fade) = animation.frames_at(time);
+        let color = animation.frames[frame][idx];
+        match fade > 0.0 {
+            true => mix(color, animation.frames[next][idx], fade),
+            false => color,
+        }
+    }
+
+    /// Whether the texture is split in the sprites of the faces of a cube.
+    pub fn is_cube_unwrap(&self) -> bool {
+        self.width as usize == self.sprite_size * 3 && self.height as usize == self.sprite_size * 4
+    }
+
     /// This function assumes the sprite is configured
     /// to be read like a cloth put over the cube-like shape.
-    pub fn get_color_of_face(&self, face: &CubeFace, x: f32, y: f32) -> Color {
+    /// Textures that aren't split in sprites look the same on every face.
+    /// `time` is how many seconds into its animation the texture is.
+    pub fn get_color_of_face(&self, face: &CubeFace, x: f32, y: f32, time: f32) -> Color {
         let sprite_size = self.sprite_size as f32;
+        if !self.is_cube_unwrap() && *face != CubeFace::NONE {
+            let uv = (Vec2::new(x, y) / sprite_size).map(|c| c.min(0.999));
+            return self.sample_at(&uv, time);
+        }
         let point = Vec2::new(x, y);
         let origin = match face {
             CubeFace::TOP => Vec2::new(sprite_size, sprite_size),
@@ -192,6 +839,189 @@ impl Texture {
         let x = point.x.clamp(origin.x, origin.x + sprite_size - 1.0) as u32;
         let y = point.y.clamp(origin.y, origin.y + sprite_size - 1.0) as u32;
 
-        self.get_pixel_color(x, y)
+        self.get_pixel_color_at(x, y, time)
+    }
+}
+
+/// Turns the texture coordinates of a face, laid out for the unwrap of a cube, into the ones of
+/// an image of the face seen from the outside with the top of the block up.
+/// The top face has north up and the bottom face south up, like in Minecraft.
+fn upright_uv(face: &CubeFace, uv: &Vec2) -> Vec2 {
+    let (u, v) = (uv.x, uv.y);
+    let upright = match face {
+        CubeFace::TOP => Vec2::new(u, v),
+        CubeFace::BOTTOM => Vec2::new(u, 1.0 - v),
+        CubeFace::FORWARDS => Vec2::new(u, 1.0 - v),
+        CubeFace::BACKWARDS => Vec2::new(1.0 - u, v),
+        CubeFace::LEFT => Vec2::new(v, 1.0 - u),
+        CubeFace::RIGHT => Vec2::new(1.0 - v, u),
+        CubeFace::NONE => Vec2::new(u, v),
+    };
+    upright.map(|c| c.clamp(0.0, 0.999))
+}
+
+/// Mixes two colors, a `fade` of 0 gives `from` and 1 gives `to`.
+fn mix(from: Color, to: Color, fade: f32) -> Color {
+    let channel = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * fade).round() as u8;
+    Color::new(
+        channel(from.r, to.r),
+        channel(from.g, to.g),
+        channel(from.b, to.b),
+    )
+}
+
+/// The `.mcmeta` file that animates an image, named like the image plus `.mcmeta`.
+fn mcmeta_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".mcmeta");
+    path.with_file_name(file_name)
+}
+
+/// The name of the texture of an image file, its name without the extension.
+fn texture_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn read_image(path: &Path) -> Result<DynamicImage, TextureError> {
+    ImageReader::open(path)
+        .map_err(|e| TextureError::Io(path.to_path_buf(), e))?
+        .decode()
+        .map_err(|e| TextureError::Decode(path.to_path_buf(), e))
+}
+
+/// Reads the names and files of a texture manifest, a missing manifest has no textures.
+fn read_manifest(path: &Path) -> Result<BTreeMap<String, String>, TextureError> {
+    match fs::read_to_string(path) {
+        Ok(source) => {
+            serde_json::from_str(&source).map_err(|e| TextureError::Manifest(path.to_path_buf(), e))
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(error) => Err(TextureError::Io(path.to_path_buf(), error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_registry_names_every_default_image() {
+        let registry = TextureRegistry::builtin();
+
+        assert_eq!(registry.id("Dirt"), Some(TextureId::DIRT));
+        assert!(registry.get(TextureId::GOLD).is_cube_unwrap());
+
+        let plant = registry.id("plant").unwrap();
+        assert!(!registry.get(plant).is_cube_unwrap());
+        assert_eq!(registry.id("bedrock"), None);
+    }
+
+    #[test]
+    fn test_missing_asset_dir_keeps_the_default_textures() {
+        let missing_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("no_such_dir");
+        let registry = TextureRegistry::new(&missing_dir);
+        assert_ne!(registry.get(TextureId::DIRT), &Texture::missing());
+        assert_eq!(
+            TextureRegistry::default().get(TextureId::DIRT),
+            &Texture::missing()
+        );
+
+        let error = Texture::load(missing_dir.join("dirt.png")).unwrap_err();
+        assert!(matches!(&error, TextureError::Io(path, _) if path.ends_with("dirt.png")));
+    }
+
+    #[test]
+    fn test_inserting_a_name_again_replaces_its_texture() {
+        let mut registry = TextureRegistry::default();
+        let white = Texture::from_colors(1, 1, vec![0xffffff.into()]);
+        let black = Texture::from_colors(1, 1, vec![0x000000.into()]);
+
+        let id = registry.insert("oak_planks", white);
+        assert_eq!(registry.insert("OAK_PLANKS", black), id);
+        assert_eq!(registry.get(id).get_pixel_color(0, 0), Color::new(0, 0, 0));
+        assert_eq!(
+            registry.insert("stone", Texture::from_colors(1, 1, vec![0.into()])),
+            TextureId::STONE
+        );
+    }
+
+    fn encode_png(image: &image::RgbImage) -> Vec<u8> {
+        let mut contents = Cursor::new(vec![]);
+        image.write_to(&mut contents, ImageFormat::Png).unwrap();
+        contents.into_inner()
+    }
+
+    #[test]
+    fn test_mcmeta_strips_are_animated() {
+        // Three frames, black, white and red
+        let strip = image::RgbImage::from_fn(2, 6, |_, y| match y / 2 {
+            0 => image::Rgb([0, 0, 0]),
+            1 => image::Rgb([255, 255, 255]),
+            _ => image::Rgb([255, 0, 0]),
+        });
+        let mcmeta = r#"{ "animation": { "frametime": 2, "interpolate": true,
+                          "frames": [1, { "index": 2, "time": 4 }] } }"#;
+        let texture =
+            Texture::decode_with_mcmeta("strip.png", &encode_png(&strip), Some(mcmeta)).unwrap();
+
+        assert!(texture.is_animated());
+        assert_eq!((texture.width, texture.height), (2, 2));
+        // White for 2 ticks fading into red, then red for 4 fading back into white
+        assert_eq!(
+            texture.get_pixel_color_at(0, 0, 0.0),
+            Color::new(255, 255, 255)
+        );
+        assert_eq!(
+            texture.get_pixel_color_at(1, 1, 0.05),
+            Color::new(255, 128, 128)
+        );
+        assert_eq!(texture.get_pixel_color_at(0, 1, 0.1), Color::new(255, 0, 0));
+        assert_eq!(
+            texture.get_pixel_color_at(0, 0, 0.3),
+            Color::new(255, 255, 255)
+        );
+
+        // The shortest frame lasts 2 ticks, split into steps while it fades
+        assert_eq!(texture.animation_step(), Some(0.1 / FADE_STEPS));
+
+        let still = Texture::decode("strip.png", &encode_png(&strip)).unwrap();
+        assert!(!still.is_animated());
+        assert_eq!(still.animation_step(), None);
+    }
+
+    #[test]
+    fn test_gifs_are_animated_with_their_delays() {
+        let frames = [[0, 0, 255, 255], [0, 255, 0, 255]].map(|color| {
+            let buffer = image::RgbaImage::from_pixel(2, 2, image::Rgba(color));
+            Frame::from_parts(buffer, 0, 0, image::Delay::from_numer_denom_ms(200, 1))
+        });
+        let mut contents = vec![];
+        image::codecs::gif::GifEncoder::new(&mut contents)
+            .encode_frames(frames)
+            .unwrap();
+        let texture: Texture = AnimatedTexture::decode("blink.gif", &contents)
+            .unwrap()
+            .into();
+
+        assert!(texture.is_animated());
+        assert_eq!(texture.get_pixel_color_at(0, 0, 0.1), Color::new(0, 0, 255));
+        assert_eq!(texture.get_pixel_color_at(0, 0, 0.3), Color::new(0, 255, 0));
+        assert_eq!(texture.get_pixel_color_at(0, 0, 0.5), Color::new(0, 0, 255));
+    }
+
+    #[test]
+    fn test_builtin_portal_water_and_magma_are_animated() {
+        let registry = TextureRegistry::builtin();
+
+        for id in [TextureId::PORTAL, TextureId::WATER, TextureId::MAGMA] {
+            assert!(registry.is_animated(id));
+            assert!(registry.get(id).is_cube_unwrap());
+        }
+        assert!(!registry.is_animated(TextureId::STONE));
+        assert!(crate::material::MAGMA.is_animated(&registry));
+        // The water fades between frames that last 3 ticks
+        assert_eq!(registry.animation_step(), Some(3.0 * TICK / FADE_STEPS));
     }
 }
@@ -0,0 +1,202 @@
+use crate::color::{Color, Radiance};
+
+/// Luminance the auto exposure maps the average of the frame to, also known as the key value.
+const AUTO_EXPOSURE_KEY: f32 = 0.18;
+/// Keeps pure black pixels from sending the logarithmic average to zero.
+const LOG_AVERAGE_DELTA: f32 = 1e-4;
+/// Linear value that gets mapped to white by the filmic operator.
+const FILMIC_WHITE_POINT: f32 = 11.2;
+
+/// How radiance values greater than 1.0 are compressed into the displayable range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMapOperator {
+    /// Anything brighter than 1.0 is clipped.
+    #[default]
+    Clamp,
+    /// `L / (1 + L)` applied over the luminance, keeps the hue of bright colors.
+    Reinhard,
+    /// John Hable's filmic curve from Uncharted 2.
+    Filmic,
+    /// Krzysztof Narkowicz's fit of the ACES reference rendering transform.
+    Aces,
+}
+
+impl ToneMapOperator {
+    /// Gets an operator by its name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "clamp" | "none" => Some(ToneMapOperator::Clamp),
+            "reinhard" => Some(ToneMapOperator::Reinhard),
+            "filmic" | "hable" => Some(ToneMapOperator::Filmic),
+            "aces" => Some(ToneMapOperator::Aces),
+            _ => None,
+        }
+    }
+
+    /// The operator that comes after this one, useful to cycle through all of them.
+    pub fn next(&self) -> Self {
+        match self {
+            ToneMapOperator::Clamp => ToneMapOperator::Reinhard,
+            ToneMapOperator::Reinhard => ToneMapOperator::Filmic,
+            ToneMapOperator::Filmic => ToneMapOperator::Aces,
+            ToneMapOperator::Aces => ToneMapOperator::Clamp,
+        }
+    }
+
+    pub fn apply(&self, radiance: Radiance) -> Radiance {
+        match self {
+            ToneMapOperator::Clamp => radiance,
+            ToneMapOperator::Reinhard => {
+                let luminance = radiance.luminance();
+                if luminance <= 0.0 {
+                    radiance
+                } else {
+                    radiance * (1.0 / (1.0 + luminance))
+                }
+            }
+            ToneMapOperator::Filmic => {
+                let white_scale = 1.0 / hable(FILMIC_WHITE_POINT);
+                map_channels(radiance, |c| hable(c) * white_scale)
+            }
+            ToneMapOperator::Aces => map_channels(radiance, |c| {
+                (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)
+            }),
+        }
+    }
+}
+
+fn map_channels(radiance: Radiance, f: impl Fn(f32) -> f32) -> Radiance {
+    Radiance::new(f(radiance.r), f(radiance.g), f(radiance.b))
+}
+
+fn hable(c: f32) -> f32 {
+    let (a, b, c_, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    ((c * (a * c + c_ * b) + d * e) / (c * (a * c + b) + d * f)) - e / f
+}
+
+/// Encodes a linear value with the sRGB transfer function.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Decodes an sRGB encoded value into a linear one.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Computes the logarithmic average of the luminance of all the pixels.
+pub fn log_average_luminance(pixels: &[Radiance]) -> f32 {
+    if pixels.is_empty() {
+        return 0.0;
+    }
+
+    let log_sum: f32 = pixels
+        .iter()
+        .map(|p| (LOG_AVERAGE_DELTA + p.luminance().max(0.0)).ln())
+        .sum();
+
+    (log_sum / pixels.len() as f32).exp()
+}
+
+/// Converts the radiance computed by the renderer into colors the screen can display.
+///
+/// The radiance is scaled by the exposure, compressed by the tone mapping operator
+/// and finally encoded with the sRGB transfer function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayTransform {
+    pub operator: ToneMapOperator,
+    /// Exposure compensation in stops, every stop doubles the brightness.
+    pub exposure: f32,
+    /// Whether or not the exposure is first adjusted to the average luminance of the frame.
+    pub auto_exposure: bool,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        DisplayTransform {
+            operator: ToneMapOperator::default(),
+            exposure: 0.0,
+            auto_exposure: false,
+        }
+    }
+}
+
+impl DisplayTransform {
+    /// Computes the factor every pixel of the frame gets multiplied by before tone mapping.
+    pub fn exposure_scale(&self, pixels: &[Radiance]) -> f32 {
+        let manual = 2f32.powf(self.exposure);
+        if !self.auto_exposure {
+            return manual;
+        }
+
+        let average = log_average_luminance(pixels);
+        if average <= LOG_AVERAGE_DELTA {
+            manual
+        } else {
+            manual * AUTO_EXPOSURE_KEY / average
+        }
+    }
+
+    /// Converts a single pixel given the scale computed by `exposure_scale`.
+    pub fn apply_pixel(&self, radiance: Radiance, exposure_scale: f32) -> Color {
+        let mapped = self.operator.apply(radiance * exposure_scale);
+        map_channels(mapped, |c| linear_to_srgb(c.clamp(0.0, 1.0))).to_color()
+    }
+
+    /// Converts a whole frame.
+    pub fn apply(&self, pixels: &[Radiance]) -> Vec<Color> {
+        let exposure_scale = self.exposure_scale(pixels);
+        pixels
+            .iter()
+            .map(|p| self.apply_pixel(*p, exposure_scale))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srgb_round_trip() {
+        for value in [0.0, 0.002, 0.1, 0.5, 0.9, 1.0] {
+            assert!((srgb_to_linear(linear_to_srgb(value)) - value).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_operators_keep_highlights_in_range() {
+        let bright = Radiance::new(10.0, 5.0, 1.5);
+
+        for operator in [
+            ToneMapOperator::Reinhard,
+            ToneMapOperator::Filmic,
+            ToneMapOperator::Aces,
+        ] {
+            let mapped = operator.apply(bright);
+            assert!(mapped.luminance() <= 1.0, "{operator:?} gave {mapped:?}");
+            assert!(mapped.r > mapped.b, "{operator:?} lost the hue");
+        }
+    }
+
+    #[test]
+    fn test_auto_exposure_maps_average_to_key() {
+        let pixels = vec![Radiance::new(4.0, 4.0, 4.0); 16];
+        let transform = DisplayTransform {
+            auto_exposure: true,
+            ..DisplayTransform::default()
+        };
+
+        let scaled = pixels[0] * transform.exposure_scale(&pixels);
+
+        assert!((scaled.luminance() - AUTO_EXPOSURE_KEY).abs() < 1e-3);
+    }
+}
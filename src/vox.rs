@@ -0,0 +1,660 @@
+//! Loads MagicaVoxel `.vox` files as a `VoxelGrid`.
+//!
+//! The models of the file are placed where the scene graph (`nTRN`, `nGRP` and `nSHP`
+//! chunks) puts them, only translations are supported. MagicaVoxel uses Z as the up axis,
+//! so the models are turned to have Y up like the rest of the scene.
+//!
+//! Every color of the palette (`RGBA`, or the default palette when there's none) becomes a
+//! `Material`, its `MATL` settings are mapped like this:
+//!
+//! * `_metal`: smooth metals reflect, `reflectivity` is `_metal * (1 - _rough)`.
+//! * `_glass` and `_blend`: `_trans` is the `transparency` and `_ior` the refractive index.
+//! * `_emit`: `_emit` times `1 + _flux` is the `emission`.
+//! * Anything else (`_diffuse`, `_media`...) is a plain diffuse material.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use glm::Vec3;
+
+use crate::{
+    color::Color,
+    material::Material,
+    voxel::{BlockId, VoxelGrid},
+};
+
+/// The rotation MagicaVoxel writes for nodes that aren't rotated.
+const IDENTITY_ROTATION: &str = "4";
+
+/// The material of palette colors without `MATL` settings.
+const BASE_VOX_MATERIAL: Material = Material {
+    diffuse: Color::new(0xFF, 0xFF, 0xFF),
+    specular: 10.0,
+    albedo: (0.9, 0.1),
+    reflectivity: 0.0,
+    transparency: 0.0,
+    refractive_index: 1.0,
+    emission: 0.0,
+    emission_color: None,
+    texture_map: None,
+    texture: None,
+};
+
+#[derive(Debug)]
+pub enum VoxError {
+    Io(PathBuf, std::io::Error),
+    Invalid(PathBuf, String),
+}
+
+impl Display for VoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VoxError::Io(path, error) => {
+                write!(f, "Couldn't read voxel model {}: {error}", path.display())
+            }
+            VoxError::Invalid(path, reason) => {
+                write!(f, "Invalid voxel model {}: {reason}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for VoxError {}
+
+/// A model of a `.vox` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxModel {
+    /// Size of the model in the x, y and z axis, Z being up.
+    pub size: [u32; 3],
+    /// The x, y, z and color index of every voxel.
+    pub voxels: Vec<[u8; 4]>,
+}
+
+/// Where a model is placed in the scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoxInstance {
+    pub model: usize,
+    /// Position of the center of the model, Z being up.
+    pub translation: [i32; 3],
+}
+
+/// The contents of a `.vox` file.
+#[derive(Debug, Clone)]
+pub struct VoxFile {
+    pub models: Vec<VoxModel>,
+    pub instances: Vec<VoxInstance>,
+    /// Colors by their color index, index 0 is never used by voxels.
+    pub palette: [Color; 256],
+    /// The `MATL` settings of every color index that has them.
+    pub materials: HashMap<u8, HashMap<String, String>>,
+}
+
+/// The palette MagicaVoxel uses for files without an `RGBA` chunk, by color index.
+///
+/// It's a 6x6x6 color cube without black followed by ramps of red, green, blue and gray.
+pub fn default_palette() -> [Color; 256] {
+    const CUBE_STEPS: [u8; 6] = [0xFF, 0xCC, 0x99, 0x66, 0x33, 0x00];
+    const RAMP_STEPS: [u8; 10] = [0xEE, 0xDD, 0xBB, 0xAA, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+
+    let cube = CUBE_STEPS.iter().flat_map(|&r| {
+        CUBE_STEPS
+            .iter()
+            .flat_map(move |&g| CUBE_STEPS.iter().map(move |&b| Color::new(r, g, b)))
+    });
+    let ramps = (0..4).flat_map(|ramp| {
+        RAMP_STEPS.iter().map(move |&value| match ramp {
+            0 => Color::new(value, 0, 0),
+            1 => Color::new(0, value, 0),
+            2 => Color::new(0, 0, value),
+            _ => Color::new(value, value, value),
+        })
+    });
+
+    let mut palette = [Color::black(); 256];
+    for (entry, color) in palette[1..].iter_mut().zip(cube.take(215).chain(ramps)) {
+        *entry = color;
+    }
+    palette
+}
+
+/// Reads the little endian values of a `.vox` file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if count > self.bytes.len() {
+            return Err("unexpected end of file".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn len(&mut self) -> Result<usize, String> {
+        usize::try_from(self.i32()?).map_err(|_| "negative length".to_string())
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.len()?;
+        Ok(String::from_utf8_lossy(self.take(len)?).to_string())
+    }
+
+    fn dict(&mut self) -> Result<HashMap<String, String>, String> {
+        let entries = self.len()?;
+        (0..entries)
+            .map(|_| Ok((self.string()?, self.string()?)))
+            .collect()
+    }
+
+    /// Reads the header of the next chunk and returns its id and content.
+    /// Children are read as the following chunks.
+    fn chunk(&mut self) -> Result<([u8; 4], Reader<'a>), String> {
+        let id = self.take(4)?;
+        let content_size = self.len()?;
+        let _children_size = self.len()?;
+        let content = self.take(content_size)?;
+        Ok(([id[0], id[1], id[2], id[3]], Reader { bytes: content }))
+    }
+}
+
+/// A node of the scene graph.
+enum SceneNode {
+    Transform { child: i32, translation: [i32; 3] },
+    Group { children: Vec<i32> },
+    Shape { models: Vec<usize> },
+}
+
+fn parse_translation(frame: &HashMap<String, String>) -> Result<[i32; 3], String> {
+    let Some(translation) = frame.get("_t") else {
+        return Ok([0; 3]);
+    };
+
+    let values: Vec<i32> = translation
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("invalid translation `{translation}`"))?;
+    match values[..] {
+        [x, y, z] => Ok([x, y, z]),
+        _ => Err(format!("invalid translation `{translation}`")),
+    }
+}
+
+/// Finds every shape of the scene graph under `id`, adding up the translations on the way.
+fn collect_instances(
+    nodes: &HashMap<i32, SceneNode>,
+    id: i32,
+    translation: [i32; 3],
+    depth: usize,
+    instances: &mut Vec<VoxInstance>,
+) -> Result<(), String> {
+    if depth > nodes.len() {
+        return Err("the scene graph has a cycle".to_string());
+    }
+
+    match nodes.get(&id) {
+        Some(SceneNode::Transform {
+            child,
+            translation: offset,
+        }) => {
+            let translation = [0, 1, 2].map(|i| translation[i].checked_add(offset[i]));
+            let [Some(x), Some(y), Some(z)] = translation else {
+                return Err(format!("the translation of node {id} is out of range"));
+            };
+            collect_instances(nodes, *child, [x, y, z], depth + 1, instances)?;
+        }
+        Some(SceneNode::Group { children }) => {
+            for child in children {
+                collect_instances(nodes, *child, translation, depth + 1, instances)?;
+            }
+        }
+        Some(SceneNode::Shape { models }) => {
+            instances.extend(
+                models
+                    .iter()
+                    .map(|&model| VoxInstance { model, translation }),
+            );
+        }
+        None => return Err(format!("the scene graph references the missing node {id}")),
+    }
+    Ok(())
+}
+
+/// Makes sure the coordinates of every voxel fit in an `i32` and that the grid
+/// holding all of them isn't too big.
+fn check_bounds(models: &[VoxModel], instances: &[VoxInstance]) -> Result<(), String> {
+    let mut min = [i32::MAX; 3];
+    let mut max = [i32::MIN; 3];
+    for instance in instances {
+        let size = models[instance.model].size;
+        for i in 0..3 {
+            // Sizes come from non negative `i32`s, so they fit
+            let corner = instance.translation[i].checked_sub((size[i] / 2) as i32);
+            let end = corner.and_then(|corner| corner.checked_add(size[i] as i32));
+            let (Some(corner), Some(end)) = (corner, end) else {
+                return Err(format!("model {} is placed out of range", instance.model));
+            };
+            min[i] = min[i].min(corner);
+            max[i] = max[i].max(end);
+        }
+    }
+    if instances.is_empty() {
+        return Ok(());
+    }
+
+    let spans = [0, 1, 2].map(|i| max[i].checked_sub(min[i]).map(|span| span as usize));
+    match spans {
+        [Some(x), Some(y), Some(z)] if VoxelGrid::cell_count([x, y, z]).is_some() => Ok(()),
+        _ => Err(format!(
+            "the models take more than {} blocks",
+            VoxelGrid::MAX_CELLS
+        )),
+    }
+}
+
+fn parse_vox(bytes: &[u8]) -> Result<VoxFile, String> {
+    let mut reader = Reader { bytes };
+    if reader.take(4)? != b"VOX " {
+        return Err("not a MagicaVoxel file".to_string());
+    }
+    let _version = reader.i32()?;
+
+    let (id, _) = reader.chunk()?;
+    if &id != b"MAIN" {
+        return Err("missing MAIN chunk".to_string());
+    }
+
+    let mut models = vec![];
+    let mut size = None;
+    let mut palette = default_palette();
+    let mut materials = HashMap::new();
+    let mut nodes = HashMap::new();
+    let mut warned_rotation = false;
+
+    while !reader.is_empty() {
+        let (id, mut content) = reader.chunk()?;
+        match &id {
+            b"SIZE" => {
+                let [x, y, z] = [content.i32()?, content.i32()?, content.i32()?];
+                size = Some([x, y, z].map(|side| side.max(0) as u32));
+            }
+            b"XYZI" => {
+                let size = size.take().ok_or("XYZI chunk without a SIZE chunk")?;
+                let count = content.len()?;
+                let voxels: Vec<[u8; 4]> = (0..count)
+                    .map(|_| Ok([content.u8()?, content.u8()?, content.u8()?, content.u8()?]))
+                    .collect::<Result<_, String>>()?;
+                if let Some(voxel) = voxels
+                    .iter()
+                    .find(|voxel| (0..3).any(|i| voxel[i] as u32 >= size[i]))
+                {
+                    return Err(format!(
+                        "voxel at {:?} is outside of its model of size {size:?}",
+                        &voxel[..3]
+                    ));
+                }
+                models.push(VoxModel { size, voxels });
+            }
+            b"RGBA" => {
+                // The color of index `i` is entry `i - 1`, the last entry is never used.
+                for entry in palette[1..].iter_mut() {
+                    let [r, g, b, _a] =
+                        [content.u8()?, content.u8()?, content.u8()?, content.u8()?];
+                    *entry = Color::new(r, g, b);
+                }
+            }
+            b"MATL" => {
+                let id = content.i32()?;
+                let properties = content.dict()?;
+                if let Ok(index @ 1..=255) = u8::try_from(id) {
+                    materials.insert(index, properties);
+                }
+            }
+            b"nTRN" => {
+                let id = content.i32()?;
+                let _attributes = content.dict()?;
+                let child = content.i32()?;
+                let _reserved = content.i32()?;
+                let _layer = content.i32()?;
+                let frame_count = content.len()?;
+                let frames = (0..frame_count)
+                    .map(|_| content.dict())
+                    .collect::<Result<Vec<_>, _>>()?;
+                let frame = frames.into_iter().next().unwrap_or_default();
+
+                let rotated = frame
+                    .get("_r")
+                    .is_some_and(|rotation| rotation != IDENTITY_ROTATION);
+                if rotated && !warned_rotation {
+                    eprintln!(
+                        "Warning: rotated voxel models aren't supported, ignoring their rotation"
+                    );
+                    warned_rotation = true;
+                }
+
+                let translation = parse_translation(&frame)?;
+                nodes.insert(id, SceneNode::Transform { child, translation });
+            }
+            b"nGRP" => {
+                let id = content.i32()?;
+                let _attributes = content.dict()?;
+                let child_count = content.len()?;
+                let children = (0..child_count)
+                    .map(|_| content.i32())
+                    .collect::<Result<_, _>>()?;
+                nodes.insert(id, SceneNode::Group { children });
+            }
+            b"nSHP" => {
+                let id = content.i32()?;
+                let _attributes = content.dict()?;
+                let model_count = content.len()?;
+                let models = (0..model_count)
+                    .map(|_| {
+                        let model = content.len()?;
+                        let _attributes = content.dict()?;
+                        Ok(model)
+                    })
+                    .collect::<Result<_, String>>()?;
+                nodes.insert(id, SceneNode::Shape { models });
+            }
+            // Layers, cameras, render settings and the like don't matter here.
+            _ => {}
+        }
+    }
+
+    let instances = if nodes.is_empty() {
+        // Files without a scene graph have all their models at the origin.
+        (0..models.len())
+            .map(|model| VoxInstance {
+                model,
+                translation: [0; 3],
+            })
+            .collect()
+    } else {
+        let mut instances = vec![];
+        collect_instances(&nodes, 0, [0; 3], 0, &mut instances)?;
+        instances
+    };
+
+    if let Some(instance) = instances.iter().find(|i| i.model >= models.len()) {
+        return Err(format!("missing model {}", instance.model));
+    }
+    check_bounds(&models, &instances)?;
+
+    Ok(VoxFile {
+        models,
+        instances,
+        palette,
+        materials,
+    })
+}
+
+fn get_f32(properties: &HashMap<String, String>, key: &str) -> Option<f32> {
+    properties
+        .get(key)
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Converts a color of the palette and its `MATL` settings into a `Material`.
+pub fn vox_material(color: Color, properties: Option<&HashMap<String, String>>) -> Material {
+    let mut material = Material {
+        diffuse: color,
+        ..BASE_VOX_MATERIAL
+    };
+    let Some(properties) = properties else {
+        return material;
+    };
+
+    let roughness = get_f32(properties, "_rough").unwrap_or(0.1).clamp(0.0, 1.0);
+    match properties.get("_type").map(String::as_str) {
+        Some("_metal") => {
+            let metal = get_f32(properties, "_metal").unwrap_or(1.0).clamp(0.0, 1.0);
+            let highlights = (0.04 + 0.96 * metal) * (1.0 - roughness);
+            material.albedo = (1.0 - highlights, highlights);
+            material.specular = (1.0 - roughness) * 100.0 + 1.0;
+            material.reflectivity = metal * (1.0 - roughness);
+        }
+        Some("_glass" | "_blend") => {
+            let transparency = get_f32(properties, "_trans")
+                .or_else(|| get_f32(properties, "_alpha"))
+                .unwrap_or(1.0)
+                .clamp(0.0, 1.0);
+            material.transparency = transparency;
+            material.reflectivity = (0.1 * (1.0 - roughness)).min(1.0 - transparency);
+            // MagicaVoxel stores the refractive index minus one.
+            material.refractive_index = 1.0 + get_f32(properties, "_ior").unwrap_or(0.5);
+        }
+        Some("_emit") => {
+            let emit = get_f32(properties, "_emit").unwrap_or(1.0).max(0.0);
+            let flux = get_f32(properties, "_flux").unwrap_or(0.0).max(0.0);
+            material.emission = emit * (1.0 + flux);
+        }
+        _ => {}
+    }
+
+    material
+}
+
+impl VoxFile {
+    /// Puts every model of the file in a single grid whose corner with the smallest
+    /// coordinates is `origin`.
+    pub fn to_voxel_grid(&self, origin: Vec3, block_size: f32) -> VoxelGrid {
+        // Every voxel in the coordinates of the scene, still with Z up
+        let voxels = self.instances.iter().flat_map(|instance| {
+            let model = &self.models[instance.model];
+            let corner = [0, 1, 2].map(|i| instance.translation[i] - (model.size[i] / 2) as i32);
+            model.voxels.iter().map(move |&[x, y, z, color]| {
+                (
+                    [
+                        corner[0] + x as i32,
+                        corner[1] + y as i32,
+                        corner[2] + z as i32,
+                    ],
+                    color,
+                )
+            })
+        });
+
+        let (min, max) = voxels.clone().fold(
+            ([i32::MAX; 3], [i32::MIN; 3]),
+            |(min, max), (position, _)| {
+                (
+                    [0, 1, 2].map(|i| min[i].min(position[i])),
+                    [0, 1, 2].map(|i| max[i].max(position[i])),
+                )
+            },
+        );
+        if min[0] > max[0] {
+            return VoxelGrid::new(origin, [0, 0, 0], block_size);
+        }
+
+        let size = [0, 1, 2].map(|i| (max[i] - min[i] + 1) as usize);
+        let mut grid = VoxelGrid::new(origin, [size[0], size[2], size[1]], block_size);
+        let mut block_ids: [Option<BlockId>; 256] = [None; 256];
+
+        for ([x, y, z], color) in voxels {
+            let id = *block_ids[color as usize].get_or_insert_with(|| {
+                let material =
+                    vox_material(self.palette[color as usize], self.materials.get(&color));
+                grid.register_material(material)
+            });
+
+            // Z up to Y up, the model's Y axis ends up going towards -Z.
+            grid.set_block_id(
+                (x - min[0]) as usize,
+                (z - min[2]) as usize,
+                (max[1] - y) as usize,
+                Some(id),
+            );
+        }
+
+        grid
+    }
+}
+
+/// Reads the `.vox` file at `file_path`.
+pub fn read_vox(file_path: impl AsRef<Path>) -> Result<VoxFile, VoxError> {
+    let file_path = file_path.as_ref();
+    let bytes = fs::read(file_path).map_err(|e| VoxError::Io(file_path.to_path_buf(), e))?;
+    parse_vox(&bytes).map_err(|reason| VoxError::Invalid(file_path.to_path_buf(), reason))
+}
+
+/// Reads the `.vox` file at `file_path` as a grid of blocks of `block_size`
+/// whose corner with the smallest coordinates is `origin`.
+pub fn load_vox(
+    file_path: impl AsRef<Path>,
+    origin: Vec3,
+    block_size: f32,
+) -> Result<VoxelGrid, VoxError> {
+    Ok(read_vox(file_path)?.to_voxel_grid(origin, block_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend((content.len() as i32).to_le_bytes());
+        bytes.extend(0i32.to_le_bytes());
+        bytes.extend(content);
+        bytes
+    }
+
+    fn ints(values: &[i32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn dict(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = ints(&[entries.len() as i32]);
+        for (key, value) in entries {
+            bytes.extend(ints(&[key.len() as i32]));
+            bytes.extend(key.as_bytes());
+            bytes.extend(ints(&[value.len() as i32]));
+            bytes.extend(value.as_bytes());
+        }
+        bytes
+    }
+
+    /// A 2x2x1 model with a red voxel and a glass voxel,
+    /// placed by the scene graph with its center at (10, 0, 0).
+    fn test_file() -> Vec<u8> {
+        test_file_at("10 0 0")
+    }
+
+    /// The model of `test_file` with its center at `translation`.
+    fn test_file_at(translation: &str) -> Vec<u8> {
+        let mut children = chunk(b"SIZE", &ints(&[2, 2, 1]));
+        children.extend(chunk(
+            b"XYZI",
+            &[ints(&[2]), vec![0, 0, 0, 1, 1, 1, 0, 2]].concat(),
+        ));
+
+        let mut palette = vec![0u8; 256 * 4];
+        palette[..8].copy_from_slice(&[255, 0, 0, 255, 200, 220, 255, 255]);
+        children.extend(chunk(b"RGBA", &palette));
+        children.extend(chunk(
+            b"MATL",
+            &[
+                ints(&[2]),
+                dict(&[("_type", "_glass"), ("_trans", "0.8"), ("_ior", "0.3")]),
+            ]
+            .concat(),
+        ));
+
+        let transform = [
+            ints(&[0]),
+            dict(&[]),
+            ints(&[1, -1, 0, 1]),
+            dict(&[("_t", translation)]),
+        ]
+        .concat();
+        children.extend(chunk(b"nTRN", &transform));
+        children.extend(chunk(
+            b"nSHP",
+            &[ints(&[1]), dict(&[]), ints(&[1, 0]), dict(&[])].concat(),
+        ));
+
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend(ints(&[150]));
+        bytes.extend(b"MAIN");
+        bytes.extend(ints(&[0, children.len() as i32]));
+        bytes.extend(children);
+        bytes
+    }
+
+    #[test]
+    fn test_parses_models_palette_materials_and_scene_graph() {
+        let file = parse_vox(&test_file()).unwrap();
+
+        assert_eq!(file.models.len(), 1);
+        assert_eq!(file.models[0].size, [2, 2, 1]);
+        assert_eq!(file.models[0].voxels, vec![[0, 0, 0, 1], [1, 1, 0, 2]]);
+        assert_eq!(
+            file.instances,
+            vec![VoxInstance {
+                model: 0,
+                translation: [10, 0, 0]
+            }]
+        );
+        assert_eq!(file.palette[1], Color::new(255, 0, 0));
+
+        let glass = vox_material(file.palette[2], file.materials.get(&2));
+        assert_eq!(glass.transparency, 0.8);
+        assert!((glass.refractive_index - 1.3).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_turns_models_to_y_up() {
+        let grid = parse_vox(&test_file())
+            .unwrap()
+            .to_voxel_grid(Vec3::zeros(), 1.0);
+
+        assert_eq!(grid.dimensions(), [2, 1, 2]);
+        assert_eq!(grid.block_count(), 2);
+        // The voxel at y = 0 ends up at the front, the one at y = 1 at the back.
+        assert_eq!(
+            grid.get_block(0, 0, 1).unwrap().diffuse,
+            Color::new(255, 0, 0)
+        );
+        assert_eq!(grid.get_block(1, 0, 0).unwrap().transparency, 0.8);
+    }
+
+    #[test]
+    fn test_rejects_models_placed_out_of_range() {
+        for translation in ["2147483647 0 0", "0 -2147483648 0"] {
+            let error = parse_vox(&test_file_at(translation)).unwrap_err();
+            assert_eq!(error, "model 0 is placed out of range");
+        }
+    }
+
+    #[test]
+    fn test_default_palette() {
+        let palette = default_palette();
+
+        assert_eq!(palette[1], Color::new(0xFF, 0xFF, 0xFF));
+        assert_eq!(palette[2], Color::new(0xFF, 0xFF, 0xCC));
+        assert_eq!(palette[215], Color::new(0x00, 0x00, 0x33));
+        assert_eq!(palette[216], Color::new(0xEE, 0x00, 0x00));
+        assert_eq!(palette[255], Color::new(0x11, 0x11, 0x11));
+    }
+}
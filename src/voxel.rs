@@ -0,0 +1,362 @@
+use glm::Vec3;
+
+use crate::{
+    cube::{face_attributes, BoxBounds},
+    material::Material,
+    raytracer::{Intersect, Traceable},
+    texture::CubeFace,
+};
+
+/// Index of a material inside the palette of a `VoxelGrid`.
+pub type BlockId = u16;
+
+/// A dense 3D grid of equally sized, axis aligned blocks.
+///
+/// Rays are intersected by walking the grid cell by cell (Amanatides & Woo),
+/// so the cost of a ray depends on how many cells it crosses and not on how
+/// many blocks there are.
+#[derive(Debug)]
+pub struct VoxelGrid {
+    /// The corner of the grid with the smallest coordinates.
+    origin: Vec3,
+    block_size: f32,
+    dimensions: [usize; 3],
+    blocks: Vec<Option<BlockId>>,
+    palette: Vec<Material>,
+    /// Whether a material was left out because the palette had no ids left.
+    palette_full: bool,
+    bounds: BoxBounds,
+}
+
+impl VoxelGrid {
+    /// The most cells a grid read from a file can have, about 1 GiB of blocks.
+    pub const MAX_CELLS: usize = 1 << 28;
+
+    /// The amount of cells of a grid with the given dimensions,
+    /// `None` if there are more than `MAX_CELLS`.
+    pub fn cell_count(dimensions: [usize; 3]) -> Option<usize> {
+        let [width, height, depth] = dimensions;
+        width
+            .checked_mul(height)?
+            .checked_mul(depth)
+            .filter(|&cells| cells <= Self::MAX_CELLS)
+    }
+
+    /// Creates a new empty VoxelGrid.
+    ///
+    /// * `origin`: The corner of the grid with the smallest coordinates.
+    /// * `dimensions`: The amount of blocks in the x, y and z axis.
+    /// * `block_size`: The length of one of the sides of every block.
+    pub fn new(origin: Vec3, dimensions: [usize; 3], block_size: f32) -> Self {
+        let [width, height, depth] = dimensions;
+        let bounds = BoxBounds {
+            min: origin,
+            max: origin + Vec3::new(width as f32, height as f32, depth as f32) * block_size,
+        };
+
+        VoxelGrid {
+            origin,
+            block_size,
+            dimensions,
+            blocks: vec![None; width * height * depth],
+            palette: vec![],
+            palette_full: false,
+            bounds,
+        }
+    }
+
+    pub fn dimensions(&self) -> [usize; 3] {
+        self.dimensions
+    }
+
+    pub fn block_size(&self) -> f32 {
+        self.block_size
+    }
+
+    pub fn origin(&self) -> Vec3 {
+        self.origin
+    }
+
+    /// Computes the center of the block at the given cell.
+    pub fn block_center(&self, x: usize, y: usize, z: usize) -> Vec3 {
+        self.origin
+            + (Vec3::new(x as f32, y as f32, z as f32) + Vec3::repeat(0.5)) * self.block_size
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> Option<usize> {
+        let [width, height, depth] = self.dimensions;
+        if x < width && y < height && z < depth {
+            Some((z * height + y) * width + x)
+        } else {
+            None
+        }
+    }
+
+    /// Adds the material to the palette if it's not there already.
+    /// Returns: The id of the material inside the palette.
+    ///
+    /// Once the palette has a material for every `BlockId` new materials get the last one,
+    /// with a warning.
+    pub fn register_material(&mut self, material: Material) -> BlockId {
+        match self.palette.iter().position(|m| m == &material) {
+            Some(id) => id as BlockId,
+            None if self.palette.len() > BlockId::MAX as usize => {
+                if !self.palette_full {
+                    eprintln!(
+                        "Warning: the voxel grid has more than {} materials, the rest use the last one",
+                        self.palette.len()
+                    );
+                    self.palette_full = true;
+                }
+                BlockId::MAX
+            }
+            None => {
+                self.palette.push(material);
+                (self.palette.len() - 1) as BlockId
+            }
+        }
+    }
+
+    /// Places a block made of `material` in the given cell, `None` empties the cell.
+    /// Cells outside the grid are ignored.
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, material: Option<Material>) {
+        let id = material.map(|m| self.register_material(m));
+        self.set_block_id(x, y, z, id);
+    }
+
+    /// Same as `set_block` but with a material already registered in the palette.
+    pub fn set_block_id(&mut self, x: usize, y: usize, z: usize, id: Option<BlockId>) {
+        if let Some(idx) = self.index(x, y, z) {
+            self.blocks[idx] = id;
+        }
+    }
+
+    /// Gets the material of the block in the given cell, if there's any.
+    pub fn get_block(&self, x: usize, y: usize, z: usize) -> Option<&Material> {
+        self.index(x, y, z)
+            .and_then(|idx| self.blocks[idx])
+            .map(|id| &self.palette[id as usize])
+    }
+
+    /// Counts how many cells are filled.
+    pub fn block_count(&self) -> usize {
+        self.blocks.iter().filter(|b| b.is_some()).count()
+    }
+}
+
+/// The face of a block a ray crossing an `axis` plane in the direction of `step` enters through.
+fn entry_face(axis: usize, step: i64) -> CubeFace {
+    match (axis, step > 0) {
+        (0, true) => CubeFace::LEFT,
+        (0, false) => CubeFace::RIGHT,
+        (1, true) => CubeFace::BOTTOM,
+        (1, false) => CubeFace::TOP,
+        (_, true) => CubeFace::BACKWARDS,
+        (_, false) => CubeFace::FORWARDS,
+    }
+}
+
+/// The index of the smallest component.
+fn min_axis(v: &Vec3) -> usize {
+    if v.x <= v.y && v.x <= v.z {
+        0
+    } else if v.y <= v.z {
+        1
+    } else {
+        2
+    }
+}
+
+impl Traceable for VoxelGrid {
+    fn bounding_box(&self) -> BoxBounds {
+        self.bounds
+    }
+
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Option<Intersect> {
+        // A grid with no cells along some axis has nothing to walk through
+        if self.blocks.is_empty() {
+            return None;
+        }
+
+        let inv_direction = Vec3::new(
+            1.0 / ray_direction.x,
+            1.0 / ray_direction.y,
+            1.0 / ray_direction.z,
+        );
+        let t_entry = self.bounds.ray_hit(ray_origin, &inv_direction)?;
+
+        // The axis whose slab was entered last is the one the ray entered through.
+        let t0 = (self.bounds.min - ray_origin).component_mul(&inv_direction);
+        let t1 = (self.bounds.max - ray_origin).component_mul(&inv_direction);
+        let near = glm::min2(&t0, &t1);
+        let mut axis = if near.x >= near.y && near.x >= near.z {
+            0
+        } else if near.y >= near.z {
+            1
+        } else {
+            2
+        };
+        let starts_inside = t_entry <= 0.0;
+
+        let entry_point = ray_origin + ray_direction * t_entry;
+        let relative = (entry_point - self.origin) / self.block_size;
+        let mut cell = [0i64; 3];
+        let mut step = [0i64; 3];
+        let mut t_max = Vec3::repeat(f32::INFINITY);
+        let mut t_delta = Vec3::repeat(f32::INFINITY);
+
+        for i in 0..3 {
+            let max_cell = self.dimensions[i] as i64 - 1;
+            cell[i] = (relative[i].floor() as i64).clamp(0, max_cell);
+
+            if ray_direction[i] > 0.0 {
+                step[i] = 1;
+                let boundary = self.origin[i] + (cell[i] + 1) as f32 * self.block_size;
+                t_max[i] = (boundary - ray_origin[i]) * inv_direction[i];
+                t_delta[i] = self.block_size * inv_direction[i];
+            } else if ray_direction[i] < 0.0 {
+                step[i] = -1;
+                let boundary = self.origin[i] + cell[i] as f32 * self.block_size;
+                t_max[i] = (boundary - ray_origin[i]) * inv_direction[i];
+                t_delta[i] = -self.block_size * inv_direction[i];
+            }
+        }
+
+        let mut distance = t_entry;
+        let mut inside_first_cell = starts_inside;
+
+        loop {
+            let [x, y, z] = cell.map(|c| c as usize);
+            if let Some(material) = self.get_block(x, y, z) {
+                let cell_min =
+                    self.origin + Vec3::new(x as f32, y as f32, z as f32) * self.block_size;
+
+                // Just like `Cube`, a ray that starts inside a block hits its exit face.
+                let face = if inside_first_cell {
+                    axis = min_axis(&t_max);
+                    distance = t_max[axis];
+                    entry_face(axis, -step[axis])
+                } else {
+                    entry_face(axis, step[axis])
+                };
+
+                let point = ray_origin + ray_direction * distance;
+                let (normal, texture_cords) =
+                    face_attributes(&face, &point, &cell_min, &Vec3::repeat(self.block_size));
+
+                return Some(Intersect {
+                    distance,
+                    point,
+                    normal,
+                    material: material.clone(),
+                    texture_cords,
+                    face,
+                });
+            }
+            inside_first_cell = false;
+
+            axis = min_axis(&t_max);
+            distance = t_max[axis];
+            cell[axis] += step[axis];
+            if cell[axis] < 0 || cell[axis] >= self.dimensions[axis] as i64 {
+                return None;
+            }
+            t_max[axis] += t_delta[axis];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cube::Cube,
+        material::{DIRT, STONE},
+    };
+
+    fn assert_same_hit(grid: &VoxelGrid, cube: &Cube, origin: Vec3, direction: Vec3) {
+        let direction = direction.normalize();
+        let expected = cube.ray_intersect(&origin, &direction).unwrap();
+        let found = grid.ray_intersect(&origin, &direction).unwrap();
+
+        assert_eq!(expected.face, found.face);
+        assert!((expected.distance - found.distance).abs() < 1e-4);
+        assert!((expected.normal - found.normal).magnitude() < 1e-4);
+        assert!((expected.texture_cords - found.texture_cords).magnitude() < 1e-3);
+    }
+
+    #[test]
+    fn test_matches_cube_intersection() {
+        let mut grid = VoxelGrid::new(Vec3::zeros(), [4, 4, 4], 1.5);
+        grid.set_block(2, 1, 3, Some(STONE));
+        let cube = Cube::new(
+            0,
+            grid.block_center(2, 1, 3),
+            1.5,
+            STONE,
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let target = grid.block_center(2, 1, 3);
+
+        for origin in [
+            Vec3::new(-4.0, 2.2, 5.1),
+            Vec3::new(10.0, 2.0, 5.3),
+            Vec3::new(3.5, 12.0, 5.0),
+            Vec3::new(3.1, -7.0, 4.9),
+            Vec3::new(3.9, 2.1, -6.0),
+            Vec3::new(4.1, 2.6, 16.0),
+        ] {
+            assert_same_hit(&grid, &cube, origin, target - origin);
+        }
+    }
+
+    #[test]
+    fn test_starting_inside_a_block_hits_its_exit_face() {
+        let mut grid = VoxelGrid::new(Vec3::zeros(), [3, 3, 3], 1.0);
+        grid.set_block(1, 1, 1, Some(STONE));
+        let cube = Cube::new(
+            0,
+            grid.block_center(1, 1, 1),
+            1.0,
+            STONE,
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+
+        assert_same_hit(
+            &grid,
+            &cube,
+            grid.block_center(1, 1, 1),
+            Vec3::new(0.3, 1.0, 0.2),
+        );
+    }
+
+    #[test]
+    fn test_cell_count_rejects_huge_grids() {
+        assert_eq!(VoxelGrid::cell_count([2, 3, 4]), Some(24));
+        assert_eq!(VoxelGrid::cell_count([100_000, 100_000, 100_000]), None);
+        assert_eq!(VoxelGrid::cell_count([usize::MAX, 2, 1]), None);
+    }
+
+    #[test]
+    fn test_full_palette_reuses_its_last_material() {
+        let mut grid = VoxelGrid::new(Vec3::zeros(), [1, 1, 1], 1.0);
+        grid.palette = vec![STONE; BlockId::MAX as usize + 1];
+
+        assert_eq!(grid.register_material(STONE), 0);
+        assert_eq!(grid.register_material(DIRT), BlockId::MAX);
+        assert_eq!(grid.palette.len(), BlockId::MAX as usize + 1);
+    }
+
+    #[test]
+    fn test_empty_grid_is_never_hit() {
+        let grid = VoxelGrid::new(Vec3::zeros(), [8, 8, 8], 1.0);
+
+        let intersect = grid.ray_intersect(&Vec3::new(-1.0, 4.0, 4.0), &Vec3::new(1.0, 0.0, 0.0));
+
+        assert!(intersect.is_none());
+
+        let flat = VoxelGrid::new(Vec3::zeros(), [0, 4, 4], 1.0);
+        let intersect = flat.ray_intersect(&Vec3::new(-1.0, 2.0, 2.0), &Vec3::new(1.0, 0.01, 0.01));
+        assert!(intersect.is_none());
+    }
+}
@@ -0,0 +1,279 @@
+use glm::{Vec2, Vec3};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    camera::Camera,
+    cube::Cube,
+    light::Light,
+    material::{Material, GOLD, MAGMA, NETHERRACK, OBSIDIAN, PORTAL, STONE},
+    scene::Scene,
+    sphere::Sphere,
+    texture::TextureRegistry,
+    voxel::VoxelGrid,
+    Model, TimeOfDay,
+};
+
+/// Builds the default scene: a nether portal standing over a platform of nether blocks.
+///
+/// The blocks of the platform are picked at random from `seed`.
+pub fn nether_portal(textures: TextureRegistry, seed: u64) -> Model {
+    let mut object_id = 0;
+    let p_width_height = 8;
+    let cube_size = 1.5;
+    // let mut cubes = vec![];
+
+    let platform = generate_platform(
+        Vec3::new(0.0, -cube_size * 1.0, 0.0),
+        p_width_height,
+        cube_size,
+        &mut ChaCha8Rng::seed_from_u64(seed),
+    );
+    let mut cubes = vec![];
+
+    let delta = Vec2::new(-1.0, 0.0) * cube_size;
+    let mut obsidian_frame = generate_rectangle(
+        object_id,
+        Vec2::new(-1.0, 0.0) * cube_size + delta,
+        Vec2::new(2.0, 4.0) * cube_size + delta,
+        -3.0 * cube_size,
+        cube_size,
+        OBSIDIAN,
+    );
+    object_id += (cubes.len() + 1) as u32;
+    cubes.append(&mut obsidian_frame);
+
+    let (mut portal_cubes, portal_lights, spheres) = generate_portal_wall(
+        object_id,
+        Vec2::new(-1.0, 0.0) * cube_size,
+        Vec2::new(1.0, 3.0) * cube_size,
+        -3.0 * cube_size,
+        cube_size,
+        PORTAL,
+    );
+    object_id += (cubes.len() + 1) as u32;
+    object_id += (spheres.len() + 1) as u32;
+    cubes.append(&mut portal_cubes);
+
+    cubes.push(Cube::new(
+        object_id,
+        Vec3::new(0.0, 0.0, -0.0) * cube_size,
+        cube_size,
+        GOLD,
+        Vec3::new(0.0, 1.0, 0.0).normalize(),
+    ));
+
+    cubes.push(Cube::new(
+        object_id,
+        Vec3::new(0.0, 0.0, -1.0) * cube_size,
+        cube_size,
+        MAGMA,
+        Vec3::new(0.0, 1.0, 0.0).normalize(),
+    ));
+    cubes.push(Cube::new(
+        object_id,
+        Vec3::new(-1.0, 0.0, -1.0) * cube_size,
+        cube_size,
+        MAGMA,
+        Vec3::new(0.0, 1.0, 0.0).normalize(),
+    ));
+    cubes.push(Cube::new(
+        object_id,
+        Vec3::new(-1.0, 0.0, -1.0) * cube_size,
+        cube_size,
+        MAGMA,
+        Vec3::new(0.0, 1.0, 0.0).normalize(),
+    ));
+    cubes.push(Cube::new(
+        object_id,
+        Vec3::new(1.0, 0.0, 1.0) * cube_size,
+        cube_size,
+        STONE,
+        Vec3::new(0.0, 1.0, 0.0).normalize(),
+    ));
+
+    // let mut water_cubes = generate_platform(
+    //     object_id,
+    //     Vec3::new(0.0, cube_size * 2.0, 0.0),
+    //     2,
+    //     gap,
+    //     cube_size,
+    //     WATER,
+    // );
+    // object_id += (cubes.len() + 1) as u32;
+    // cubes.append(&mut water_cubes);
+
+    let daytime = TimeOfDay::Day;
+    let top_light = daytime.top_light();
+
+    let ambient_light = 0.15;
+
+    let camera = Camera::new(
+        Vec3::new(0.0, 0.0, 10.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+
+    let mut scene = Scene::default();
+    scene.push(platform);
+    scene.extend(cubes);
+    scene.extend(spheres);
+
+    Model {
+        scene,
+        camera,
+        top_light,
+        lights: portal_lights,
+        ambient_light,
+        textures,
+        sky_color: daytime.sky_color(),
+        daytime,
+    }
+}
+
+/// Generates a square platform of randomly chosen blocks one block tall.
+///
+/// * `center`: The center of the block in the middle of the platform.
+/// * `size`: How many blocks wide and deep the platform is.
+/// * `rng`: Picks the blocks, the same numbers give the same platform.
+pub fn generate_platform<R: Rng + ?Sized>(
+    center: Vec3,
+    size: u16,
+    cube_size: f32,
+    rng: &mut R,
+) -> VoxelGrid {
+    let half_size = (size / 2) as f32;
+    let origin = center - Vec3::new(half_size + 0.5, 0.5, half_size + 0.5) * cube_size;
+    let size = size as usize;
+    let mut platform = VoxelGrid::new(origin, [size, 1, size], cube_size);
+
+    for z in 0..size {
+        for x in 0..size {
+            let rn: f32 = rng.gen();
+            let material = if rn < 0.6 {
+                NETHERRACK
+            } else if rn < 0.8 {
+                STONE
+            } else if rn < 0.95 {
+                MAGMA
+            } else {
+                GOLD
+            };
+            platform.set_block(x, 0, z, Some(material));
+        }
+    }
+
+    platform
+}
+
+/// Generates the outline of a rectangle made of cubes on the plane at depth `z`.
+///
+/// * `id_count`: The id of the first cube, the rest use the following ids.
+/// * `start`: The bottom left corner of the rectangle.
+/// * `end`: The top right corner of the rectangle.
+pub fn generate_rectangle(
+    id_count: u32,
+    start: Vec2,
+    end: Vec2,
+    z: f32,
+    cube_size: f32,
+    material: Material,
+) -> Vec<Cube> {
+    let mut object_id = id_count;
+    let endx = (end.x - start.x) as i32;
+    let endy = (end.y - start.y) as i32;
+
+    let top_and_bottom = vec![start.y, end.y].into_iter().flat_map(|ypos| {
+        let material = material.clone();
+        (0..endx)
+            .map(|xpos| xpos as f32 * cube_size)
+            .map(move |xpos| {
+                let id = object_id;
+                object_id += 1;
+
+                Cube::new(
+                    id,
+                    Vec3::new(xpos + start.x, ypos, z),
+                    cube_size,
+                    material.clone(),
+                    Vec3::new(0.0, 1.0, 0.0).normalize(),
+                )
+            })
+    });
+    // let top_and_bottom = vec![].into_iter();
+
+    let sides = vec![start.x, end.x].into_iter().flat_map(|xpos| {
+        let material = material.clone();
+        (1..(endy - 1))
+            .map(|ypos| ypos as f32 * cube_size)
+            .map(move |ypos| {
+                let id = object_id;
+                object_id += 1;
+
+                Cube::new(
+                    id,
+                    Vec3::new(xpos, ypos + start.y, z),
+                    cube_size,
+                    material.clone(),
+                    Vec3::new(0.0, 1.0, 0.0).normalize(),
+                )
+            })
+    });
+    // let sides = vec![].into_iter();
+
+    top_and_bottom.chain(sides).collect()
+}
+
+/// Generates a filled wall of cubes on the plane at depth `z` and the lights it emits.
+/// Returns: A tuple in the form of (cubes, lights, spheres).
+pub fn generate_portal_wall(
+    id_count: u32,
+    start: Vec2,
+    end: Vec2,
+    z: f32,
+    cube_size: f32,
+    material: Material,
+) -> (Vec<Cube>, Vec<Light>, Vec<Sphere>) {
+    let mut object_id = id_count;
+    let endx = (end.x - start.x) as i32;
+    let endy = (end.y - start.y) as i32;
+
+    let cubes = (0..endy)
+        .map(|ypos| ypos as f32 * cube_size)
+        .flat_map(|ypos| {
+            let material = material.clone();
+            (0..endx)
+                .map(|xpos| xpos as f32 * cube_size)
+                .map(move |xpos| {
+                    let id = object_id;
+                    object_id += 1;
+
+                    Cube::new(
+                        id,
+                        Vec3::new(xpos + start.x, ypos, z),
+                        cube_size,
+                        material.clone(),
+                        Vec3::new(0.0, 1.0, 0.0).normalize(),
+                    )
+                })
+        })
+        .collect();
+
+    let light_position = Vec3::new((end.x + start.x) / 2.0, (end.y + start.y) / 2.0, z + 1.0);
+    let light_sources = vec![Light {
+        position: light_position,
+        color: 0x361B6F.into(),
+        intensity: 0.25,
+    }];
+
+    // let spheres = vec![Sphere {
+    //     id: id_count,
+    //     center: light_position,
+    //     radius: 0.2,
+    //     material: RUBBER,
+    // }];
+
+    let spheres = vec![];
+
+    (cubes, light_sources, spheres)
+}